@@ -0,0 +1,641 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// An arbitrary-precision integer, stored as a sign and a magnitude of
+/// base 2^32 limbs, least significant first. The magnitude has no leading
+/// zero limbs, so zero is the empty magnitude and is never negative.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+	negative: bool,
+	magnitude: Vec<u32>,
+}
+
+const LIMB_BITS: u32 = u32::BITS;
+
+/// The largest power of ten that fits a limb, for converting to decimal.
+const DECIMAL_BASE: u32 = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+impl BigInt {
+	pub fn zero() -> Self {
+		Self { negative: false, magnitude: Vec::new() }
+	}
+
+	fn from_parts(negative: bool, mut magnitude: Vec<u32>) -> Self {
+		while magnitude.last() == Some(&0) {
+			magnitude.pop();
+		}
+
+		let negative = negative && !magnitude.is_empty();
+		Self { negative, magnitude }
+	}
+
+	/// Parses digits in `radix`, without sign, prefix or separators.
+	pub fn parse(digits: &str, radix: u32) -> Option<Self> {
+		if digits.is_empty() {
+			return None;
+		}
+
+		let mut magnitude = Vec::new();
+		for c in digits.chars() {
+			mul_add_small(&mut magnitude, radix, c.to_digit(radix)?);
+		}
+
+		Some(Self::from_parts(false, magnitude))
+	}
+
+	/// The integer part of `f`, or `None` if it is NaN or infinite.
+	pub fn from_f64(f: f64) -> Option<Self> {
+		if !f.is_finite() {
+			return None;
+		}
+
+		let whole = f.trunc().abs();
+		if whole == 0.0 {
+			return Some(Self::zero());
+		}
+
+		// A whole number of at least one is normal: its value is the 53-bit
+		// mantissa, implicit bit included, scaled by a power of two.
+		let bits = whole.to_bits();
+		let exponent = ((bits >> 52) & 0x7ff) as i32 - 1075;
+		let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+
+		let magnitude = if exponent >= 0 {
+			shift_left(&[mantissa as u32, (mantissa >> 32) as u32], exponent as u32)
+		} else {
+			let mantissa = mantissa >> -exponent;
+			vec![mantissa as u32, (mantissa >> 32) as u32]
+		};
+
+		Some(Self::from_parts(f < 0.0, magnitude))
+	}
+
+	pub fn is_zero(&self) -> bool {
+		self.magnitude.is_empty()
+	}
+
+	pub fn is_odd(&self) -> bool {
+		self.magnitude.first().is_some_and(|limb| limb & 1 == 1)
+	}
+
+	/// The number of significant bits in the magnitude; zero has none.
+	pub fn bits(&self) -> u64 {
+		match self.magnitude.last() {
+			Some(top) => self.magnitude.len() as u64 * LIMB_BITS as u64 - top.leading_zeros() as u64,
+			None => 0,
+		}
+	}
+
+	/// The value as an `i64`, if it fits.
+	pub fn to_i64(&self) -> Option<i64> {
+		if self.magnitude.len() > 2 {
+			return None;
+		}
+
+		let magnitude = self.magnitude.iter().rev().fold(0u64, |acc, &limb| acc << LIMB_BITS | limb as u64);
+		if self.negative {
+			0i64.checked_sub_unsigned(magnitude)
+		} else {
+			i64::try_from(magnitude).ok()
+		}
+	}
+
+	/// The nearest float, ties to even, or an infinity if the value is out
+	/// of range. The magnitude is rounded once: its top 64 bits, with any
+	/// lower set bit folded into the last one, round like the whole value.
+	pub fn to_f64(&self) -> f64 {
+		let shift = self.bits().saturating_sub(64);
+		let (limb, offset) = ((shift / LIMB_BITS as u64) as usize, shift % LIMB_BITS as u64);
+		let window = self.magnitude[limb..].iter().take(3).rev()
+			.fold(0u128, |acc, &limb| acc << LIMB_BITS | limb as u128);
+		let sticky = self.magnitude[..limb].iter().any(|&limb| limb != 0) || window & ((1 << offset) - 1) != 0;
+
+		let top = (window >> offset) as u64 | sticky as u64;
+		let magnitude = top as f64 * 2f64.powi(shift.min(f64::MAX_EXP as u64) as i32);
+		if self.negative { -magnitude } else { magnitude }
+	}
+
+	/// Compares with a float by exact value. `None` if `f` is NaN.
+	pub fn partial_cmp_f64(&self, f: f64) -> Option<Ordering> {
+		if f.is_nan() {
+			return None;
+		}
+		if f.is_infinite() {
+			return Some(if f > 0.0 { Ordering::Less } else { Ordering::Greater });
+		}
+
+		let whole = Self::from_f64(f)?;
+		Some(self.cmp(&whole).then(0.0.partial_cmp(&(f - f.trunc()))?))
+	}
+
+	/// The quotient rounded towards zero and the remainder, which takes the
+	/// sign of `self`. `None` if `divisor` is zero.
+	pub fn div_rem(&self, divisor: &Self) -> Option<(Self, Self)> {
+		if divisor.is_zero() {
+			return None;
+		}
+
+		let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &divisor.magnitude);
+		Some((
+			Self::from_parts(self.negative != divisor.negative, quotient),
+			Self::from_parts(self.negative, remainder),
+		))
+	}
+
+	/// The quotient rounded towards negative infinity. `None` if `divisor`
+	/// is zero.
+	pub fn div_floor(&self, divisor: &Self) -> Option<Self> {
+		let (quotient, remainder) = self.div_rem(divisor)?;
+		if !remainder.is_zero() && remainder.negative != divisor.negative {
+			return Some(&quotient - &Self::from(1));
+		}
+		Some(quotient)
+	}
+
+	pub fn pow(&self, mut exponent: u32) -> Self {
+		let mut result = Self::from(1);
+		let mut base = self.clone();
+		while exponent > 0 {
+			if exponent & 1 == 1 {
+				result = &result * &base;
+			}
+			exponent >>= 1;
+			if exponent > 0 {
+				base = &base * &base;
+			}
+		}
+		result
+	}
+
+	/// A rough count of the bytes the value keeps on the heap.
+	pub fn heap_size(&self) -> usize {
+		self.magnitude.capacity() * mem::size_of::<u32>()
+	}
+}
+
+impl From<i64> for BigInt {
+	fn from(n: i64) -> Self {
+		let magnitude = n.unsigned_abs();
+		Self::from_parts(n < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+	}
+}
+
+impl Ord for BigInt {
+	fn cmp(&self, other: &Self) -> Ordering {
+		match (self.negative, other.negative) {
+			(false, true) => Ordering::Greater,
+			(true, false) => Ordering::Less,
+			(false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+			(true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+		}
+	}
+}
+
+impl PartialOrd for BigInt {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Neg for &BigInt {
+	type Output = BigInt;
+
+	fn neg(self) -> BigInt {
+		BigInt::from_parts(!self.negative, self.magnitude.clone())
+	}
+}
+
+impl Add for &BigInt {
+	type Output = BigInt;
+
+	fn add(self, other: &BigInt) -> BigInt {
+		if self.negative == other.negative {
+			return BigInt::from_parts(self.negative, add_magnitude(&self.magnitude, &other.magnitude));
+		}
+
+		// Opposite signs: subtract the smaller magnitude from the larger,
+		// which decides the sign.
+		match cmp_magnitude(&self.magnitude, &other.magnitude) {
+			Ordering::Less => BigInt::from_parts(other.negative, sub_magnitude(&other.magnitude, &self.magnitude)),
+			_ => BigInt::from_parts(self.negative, sub_magnitude(&self.magnitude, &other.magnitude)),
+		}
+	}
+}
+
+impl Sub for &BigInt {
+	type Output = BigInt;
+
+	fn sub(self, other: &BigInt) -> BigInt {
+		self + &-other
+	}
+}
+
+impl Mul for &BigInt {
+	type Output = BigInt;
+
+	fn mul(self, other: &BigInt) -> BigInt {
+		BigInt::from_parts(self.negative != other.negative, mul_magnitude(&self.magnitude, &other.magnitude))
+	}
+}
+
+impl fmt::Display for BigInt {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.is_zero() {
+			return write!(f, "0");
+		}
+
+		// Peel off nine decimal digits at a time, least significant first.
+		let mut chunks = Vec::new();
+		let mut magnitude = self.magnitude.clone();
+		while !magnitude.is_empty() {
+			chunks.push(div_rem_small(&mut magnitude, DECIMAL_BASE));
+		}
+
+		if self.negative {
+			write!(f, "-")?;
+		}
+
+		let mut chunks = chunks.iter().rev();
+		if let Some(first) = chunks.next() {
+			write!(f, "{}", first)?;
+		}
+		for chunk in chunks {
+			write!(f, "{:0width$}", chunk, width = DECIMAL_DIGITS)?;
+		}
+		Ok(())
+	}
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+	a.len().cmp(&b.len()).then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+	let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+
+	let mut sum = Vec::with_capacity(long.len() + 1);
+	let mut carry = 0u64;
+	for (i, &limb) in long.iter().enumerate() {
+		let total = limb as u64 + short.get(i).copied().unwrap_or(0) as u64 + carry;
+		sum.push(total as u32);
+		carry = total >> LIMB_BITS;
+	}
+	if carry > 0 {
+		sum.push(carry as u32);
+	}
+	sum
+}
+
+/// `a - b`, where `a` is at least `b`.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+	let mut difference = Vec::with_capacity(a.len());
+	let mut borrow = 0i64;
+	for (i, &limb) in a.iter().enumerate() {
+		let total = limb as i64 - b.get(i).copied().unwrap_or(0) as i64 - borrow;
+		difference.push(total as u32);
+		borrow = (total < 0) as i64;
+	}
+	difference
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+	if a.is_empty() || b.is_empty() {
+		return Vec::new();
+	}
+
+	let mut product = vec![0u32; a.len() + b.len()];
+	for (i, &x) in a.iter().enumerate() {
+		let mut carry = 0u64;
+		for (j, &y) in b.iter().enumerate() {
+			let total = product[i + j] as u64 + x as u64 * y as u64 + carry;
+			product[i + j] = total as u32;
+			carry = total >> LIMB_BITS;
+		}
+		product[i + b.len()] = carry as u32;
+	}
+	product
+}
+
+/// `magnitude * factor + addend`, in place.
+fn mul_add_small(magnitude: &mut Vec<u32>, factor: u32, addend: u32) {
+	let mut carry = addend as u64;
+	for limb in magnitude.iter_mut() {
+		let total = *limb as u64 * factor as u64 + carry;
+		*limb = total as u32;
+		carry = total >> LIMB_BITS;
+	}
+	if carry > 0 {
+		magnitude.push(carry as u32);
+	}
+}
+
+/// Divides `magnitude` by `divisor` in place and returns the remainder.
+fn div_rem_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+	let mut remainder = 0u64;
+	for limb in magnitude.iter_mut().rev() {
+		let current = remainder << LIMB_BITS | *limb as u64;
+		*limb = (current / divisor as u64) as u32;
+		remainder = current % divisor as u64;
+	}
+
+	while magnitude.last() == Some(&0) {
+		magnitude.pop();
+	}
+	remainder as u32
+}
+
+fn shift_left(magnitude: &[u32], bits: u32) -> Vec<u32> {
+	let limbs = (bits / LIMB_BITS) as usize;
+	let bits = bits % LIMB_BITS;
+
+	let mut shifted = vec![0u32; limbs];
+	let mut carry = 0u32;
+	for &limb in magnitude {
+		shifted.push(if bits == 0 { limb } else { limb << bits | carry });
+		carry = if bits == 0 { 0 } else { limb >> (LIMB_BITS - bits) };
+	}
+	shifted.push(carry);
+	shifted
+}
+
+/// Long division of magnitudes, following Knuth's Algorithm D (TAOCP vol.
+/// 2, 4.3.1). `divisor` must not be zero.
+fn div_rem_magnitude(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+	if cmp_magnitude(dividend, divisor) == Ordering::Less {
+		return (Vec::new(), dividend.to_vec());
+	}
+
+	if let [divisor] = divisor {
+		let mut quotient = dividend.to_vec();
+		let remainder = div_rem_small(&mut quotient, *divisor);
+		return (quotient, vec![remainder]);
+	}
+
+	// Normalize so the divisor's top limb has its high bit set, which keeps
+	// each estimated quotient limb at most two too large.
+	let n = divisor.len();
+	let m = dividend.len() - n;
+	let shift = divisor[n - 1].leading_zeros();
+	let v = shift_left(divisor, shift);
+	let mut u = shift_left(dividend, shift);
+	u.resize(dividend.len() + 1, 0);
+
+	let base = 1u64 << LIMB_BITS;
+	let mut quotient = vec![0u32; m + 1];
+	for j in (0..=m).rev() {
+		let numerator = (u[j + n] as u64) << LIMB_BITS | u[j + n - 1] as u64;
+		let mut estimate = numerator / v[n - 1] as u64;
+		let mut remainder = numerator % v[n - 1] as u64;
+		while estimate >= base || estimate * v[n - 2] as u64 > (remainder << LIMB_BITS | u[j + n - 2] as u64) {
+			estimate -= 1;
+			remainder += v[n - 1] as u64;
+			if remainder >= base {
+				break;
+			}
+		}
+
+		// Subtract `estimate * v` from the current window of `u`.
+		let mut borrow = 0i64;
+		for i in 0..n {
+			let product = estimate * v[i] as u64;
+			let total = u[i + j] as i64 - borrow - (product & 0xffff_ffff) as i64;
+			u[i + j] = total as u32;
+			borrow = (product >> LIMB_BITS) as i64 - (total >> LIMB_BITS);
+		}
+		let total = u[j + n] as i64 - borrow;
+		u[j + n] = total as u32;
+
+		// The estimate was one too large: add the divisor back.
+		if total < 0 {
+			estimate -= 1;
+			let mut carry = 0u64;
+			for i in 0..n {
+				let total = u[i + j] as u64 + v[i] as u64 + carry;
+				u[i + j] = total as u32;
+				carry = total >> LIMB_BITS;
+			}
+			u[j + n] = u[j + n].wrapping_add(carry as u32);
+		}
+
+		quotient[j] = estimate as u32;
+	}
+
+	// Undo the normalization to recover the remainder.
+	let mut remainder = vec![0u32; n];
+	for i in 0..n {
+		remainder[i] = if shift == 0 {
+			u[i]
+		} else {
+			u[i] >> shift | u[i + 1] << (LIMB_BITS - shift)
+		};
+	}
+	(quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn big(digits: &str) -> BigInt {
+		match digits.strip_prefix('-') {
+			Some(digits) => -&big(digits),
+			None => BigInt::parse(digits, 10).expect("valid decimal digits"),
+		}
+	}
+
+	fn hex(digits: &str) -> BigInt {
+		BigInt::parse(digits, 16).expect("valid hex digits")
+	}
+
+	/// Checks `a = q * d + r` with `|r| < |d|` and `r` taking the sign of
+	/// `a`, and that the floored quotient brackets `a`.
+	fn check_division(a: &BigInt, d: &BigInt) {
+		let (q, r) = a.div_rem(d).expect("nonzero divisor");
+		assert_eq!(&(&q * d) + &r, *a);
+		assert_eq!(cmp_magnitude(&r.magnitude, &d.magnitude), Ordering::Less);
+		assert!(r.is_zero() || r.negative == a.negative);
+
+		let floor = a.div_floor(d).expect("nonzero divisor");
+		let low = &floor * d;
+		let high = &(&floor + &BigInt::from(1)) * d;
+		if d.negative {
+			assert!(high < *a && *a <= low);
+		} else {
+			assert!(low <= *a && *a < high);
+		}
+	}
+
+	#[test]
+	fn div_rem_and_div_floor_signs() {
+		let cases = [
+			(7, 2, 3, 1, 3),
+			(-7, 2, -3, -1, -4),
+			(7, -2, -3, 1, -4),
+			(-7, -2, 3, -1, 3),
+			(6, -3, -2, 0, -2),
+			(-6, 3, -2, 0, -2),
+			(1, 5, 0, 1, 0),
+			(-1, 5, 0, -1, -1),
+		];
+		for (a, d, quotient, remainder, floor) in cases {
+			let (a, d) = (BigInt::from(a), BigInt::from(d));
+			let (q, r) = a.div_rem(&d).unwrap();
+			assert_eq!((q, r), (BigInt::from(quotient), BigInt::from(remainder)));
+			assert_eq!(a.div_floor(&d), Some(BigInt::from(floor)));
+		}
+	}
+
+	#[test]
+	fn multi_limb_signs() {
+		let a = big("123456789012345678901234567890");
+		let d = big("1000000000000000000007");
+		for (a, d) in [(a.clone(), d.clone()), (-&a, d.clone()), (a.clone(), -&d), (-&a, -&d)] {
+			check_division(&a, &d);
+		}
+
+		let (q, r) = (-&a).div_rem(&d).unwrap();
+		assert_eq!(q.to_string(), "-123456789");
+		assert_eq!(r.to_string(), "-12345678900370370367");
+		assert_eq!((-&a).div_floor(&d).unwrap().to_string(), "-123456790");
+	}
+
+	#[test]
+	fn zero_remainders_are_not_negative() {
+		let (_, r) = big("-340282366920938463463374607431768211456").div_rem(&big("18446744073709551616")).unwrap();
+		assert!(r.is_zero() && !r.negative);
+	}
+
+	#[test]
+	fn division_by_zero() {
+		assert_eq!(BigInt::from(1).div_rem(&BigInt::zero()), None);
+		assert_eq!(BigInt::from(1).div_floor(&BigInt::zero()), None);
+	}
+
+	#[test]
+	fn add_back_step() {
+		// Each of these makes Algorithm D's estimated quotient limb one too
+		// large after the two-limb refinement, so the divisor is added back.
+		let cases = [
+			("7fffffff8000000000007fff80000001", "ffffffff00000000ffffffff", "7fffffff", "fffffffe8000800100000000"),
+			("30000000000000002000000017fffffff", "8000000080000000fffffffe", "5fffffff9", "7fffffff800000147ffffff1"),
+			("8000800000010000fffffffffffe", "8000ffffffff00007fff", "ffff0001", "8000ffff80027ffe7fff"),
+		];
+		for (a, d, quotient, remainder) in cases {
+			let (a, d) = (hex(a), hex(d));
+			assert_eq!(a.div_rem(&d), Some((hex(quotient), hex(remainder))));
+			for (a, d) in [(-&a, d.clone()), (a.clone(), -&d), (-&a, -&d)] {
+				check_division(&a, &d);
+			}
+		}
+	}
+
+	#[test]
+	fn single_limb_divisors() {
+		let (q, r) = big("1267650600228229401496703205376").div_rem(&BigInt::from(3)).unwrap();
+		assert_eq!((q.to_string(), r.to_string()), ("422550200076076467165567735125".to_string(), "1".to_string()));
+
+		let a = big("340282366920938463463374607431768223801");
+		let (q, r) = a.div_rem(&BigInt::from(u32::MAX as i64)).unwrap();
+		assert_eq!((q.to_string(), r.to_string()), ("79228162532711081671548469249".to_string(), "12346".to_string()));
+		check_division(&-&a, &BigInt::from(u32::MAX as i64));
+	}
+
+	#[test]
+	fn smaller_dividends() {
+		let (a, d) = (big("12345"), big("18446744073709551616"));
+		assert_eq!(a.div_rem(&d), Some((BigInt::zero(), a.clone())));
+		assert_eq!((-&a).div_floor(&d), Some(BigInt::from(-1)));
+	}
+
+	#[test]
+	fn to_i64_boundaries() {
+		for n in [0, 1, -1, i64::MAX, i64::MAX - 1, i64::MIN, i64::MIN + 1, u32::MAX as i64, -(u32::MAX as i64) - 1] {
+			assert_eq!(BigInt::from(n).to_i64(), Some(n));
+		}
+
+		let one = BigInt::from(1);
+		assert_eq!((&BigInt::from(i64::MAX) + &one).to_i64(), None);
+		assert_eq!((&BigInt::from(i64::MIN) - &one).to_i64(), None);
+		assert_eq!(big("18446744073709551616").to_i64(), None);
+		assert_eq!(big("-18446744073709551616").to_i64(), None);
+	}
+
+	#[test]
+	fn from_f64_truncates_exactly() {
+		assert_eq!(BigInt::from_f64(0.0), Some(BigInt::zero()));
+		assert_eq!(BigInt::from_f64(-0.0), Some(BigInt::zero()));
+		assert_eq!(BigInt::from_f64(-0.75), Some(BigInt::zero()));
+		assert_eq!(BigInt::from_f64(1.5), Some(BigInt::from(1)));
+		assert_eq!(BigInt::from_f64(-1.5), Some(BigInt::from(-1)));
+		assert_eq!(BigInt::from_f64(9007199254740993.0), Some(BigInt::from(9007199254740992)));
+		assert_eq!(BigInt::from_f64(2f64.powi(100)), Some(BigInt::from(2).pow(100)));
+		assert_eq!(BigInt::from_f64(f64::MAX).map(|n| n.to_string()), Some(
+			"179769313486231570814527423731704356798070567525844996598917476803157260780028538760589558632766878\
+			171540458953514382464234321326889464182768467546703537516986049910576551282076245490090389328944075\
+			868508455133942304583236903222948165808559332123348274797826204144723168738177180919299881250404026\
+			184124858368".to_string()
+		));
+		assert_eq!(BigInt::from_f64(f64::NAN), None);
+		assert_eq!(BigInt::from_f64(f64::INFINITY), None);
+		assert_eq!(BigInt::from_f64(f64::NEG_INFINITY), None);
+	}
+
+	#[test]
+	fn to_f64_rounds_once() {
+		let two = |n| 2f64.powi(n);
+		// Just above half an ulp, in a bit that rounding limb by limb loses.
+		assert_eq!(hex("800000000000040000000001").to_f64(), two(95) + two(43));
+		assert_eq!(hex("800000000000040000000000").to_f64(), two(95));
+		assert_eq!(hex("8000000000000c0000000000").to_f64(), two(95) + two(44));
+		assert_eq!((-&hex("800000000000040000000001")).to_f64(), -(two(95) + two(43)));
+
+		assert_eq!(BigInt::from(i64::MAX).to_f64(), two(63));
+		assert_eq!(BigInt::from(9007199254740993).to_f64(), 9007199254740992.0);
+		assert_eq!(BigInt::zero().to_f64(), 0.0);
+		assert_eq!(BigInt::from_f64(f64::MAX).unwrap().to_f64(), f64::MAX);
+		assert_eq!((&BigInt::from(2).pow(1024) - &BigInt::from(1)).to_f64(), f64::INFINITY);
+		assert_eq!((-&BigInt::from(2).pow(2000)).to_f64(), f64::NEG_INFINITY);
+	}
+
+	#[test]
+	fn bits_and_parity() {
+		assert_eq!(BigInt::zero().bits(), 0);
+		assert_eq!(BigInt::from(-1).bits(), 1);
+		assert_eq!(BigInt::from(u32::MAX as i64).bits(), 32);
+		assert_eq!(BigInt::from(2).pow(100).bits(), 101);
+		assert!(BigInt::from(-3).is_odd() && !BigInt::from(2).pow(64).is_odd() && !BigInt::zero().is_odd());
+	}
+
+	#[test]
+	fn compares_with_floats() {
+		let n = BigInt::from(9007199254740993);
+		assert_eq!(n.partial_cmp_f64(9007199254740992.0), Some(Ordering::Greater));
+		assert_eq!(BigInt::from(5).partial_cmp_f64(5.5), Some(Ordering::Less));
+		assert_eq!(BigInt::from(-5).partial_cmp_f64(-5.5), Some(Ordering::Greater));
+		assert_eq!(BigInt::from(5).partial_cmp_f64(5.0), Some(Ordering::Equal));
+		assert_eq!(BigInt::from(2).pow(1024).partial_cmp_f64(f64::MAX), Some(Ordering::Greater));
+		assert_eq!(BigInt::zero().partial_cmp_f64(f64::NAN), None);
+	}
+
+	#[test]
+	fn display() {
+		assert_eq!(BigInt::zero().to_string(), "0");
+		assert_eq!(BigInt::from(-42).to_string(), "-42");
+		assert_eq!(BigInt::from(1_000_000_000).to_string(), "1000000000");
+		assert_eq!(BigInt::from(1_000_000_000_000_000_005).to_string(), "1000000000000000005");
+		assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+		assert_eq!(BigInt::from(2).pow(200).to_string(), "1606938044258990275541962092341162602522202993782792835301376");
+	}
+
+	#[test]
+	fn parses_radixes() {
+		assert_eq!(hex("ffffffffffffffffffff").to_string(), "1208925819614629174706175");
+		assert_eq!(BigInt::parse("1010", 2), Some(BigInt::from(10)));
+		assert_eq!(BigInt::parse("0000", 10), Some(BigInt::zero()));
+		assert_eq!(BigInt::parse("", 10), None);
+		assert_eq!(BigInt::parse("12a", 10), None);
+	}
+}
@@ -3,16 +3,36 @@ use std::{cell::RefCell, rc::Rc};
 use num_derive::FromPrimitive;
 use crate::value::Value;
 
-#[derive(FromPrimitive)]
+/// Instructions that index the constant pool or the global slots come in a
+/// short form with a one-byte operand and a `Long` form with a three-byte,
+/// big-endian operand for indices past 255.
+#[derive(Clone, Copy, FromPrimitive)]
 pub enum OpCode {
 	OpConstant,
+	OpConstantLong,
 	OpNil,
 	OpTrue,
 	OpFalse,
 	OpPop,
+	OpDup,
+	OpSwap,
+	OpOver,
+	OpGetLocal,
+	OpSetLocal,
+	OpGetUpvalue,
+	OpSetUpvalue,
+	OpGetProperty,
+	OpGetPropertyLong,
+	OpSetProperty,
+	OpSetPropertyLong,
+	OpGetSuper,
+	OpGetSuperLong,
 	OpDefineGlobal,
+	OpDefineGlobalLong,
 	OpGetGlobal,
+	OpGetGlobalLong,
 	OpSetGlobal,
+	OpSetGlobalLong,
 	OpEqual,
 	OpGreater,
 	OpLess,
@@ -20,16 +40,71 @@ pub enum OpCode {
 	OpSubtract,
 	OpMultiply,
 	OpDivide,
+	OpModulo,
+	OpIntDivide,
+	OpPower,
+	OpBitAnd,
+	OpBitOr,
+	OpBitXor,
+	OpBitNot,
+	OpShiftLeft,
+	OpShiftRight,
 	OpNot,
 	OpNegate,
 	OpPrint,
+	OpJump,
+	OpJumpIfFalse,
+	OpJumpTable,
+	OpLoop,
+	OpCall,
+	OpInvoke,
+	OpInvokeLong,
+	OpSuperInvoke,
+	OpSuperInvokeLong,
+	OpClosure,
+	OpClosureLong,
+	OpCloseUpvalue,
+	OpClass,
+	OpClassLong,
+	OpInherit,
+	OpMethod,
+	OpMethodLong,
     OpReturn,
 }
 
+impl OpCode {
+	/// Whether the instruction's constant operand is three bytes wide.
+	pub fn is_long(self) -> bool {
+		matches!(
+			self,
+			OpCode::OpConstantLong | OpCode::OpGetPropertyLong | OpCode::OpSetPropertyLong
+				| OpCode::OpGetSuperLong | OpCode::OpInvokeLong | OpCode::OpSuperInvokeLong
+				| OpCode::OpClosureLong | OpCode::OpClassLong | OpCode::OpMethodLong
+		)
+	}
+}
+
+/// A run of bytecode compiled from the same source line, starting at
+/// `offset` and lasting until the next run starts.
+#[derive(Debug)]
+struct LineRun {
+	offset: usize,
+	line: usize,
+}
+
+/// A block of bytecode. Line numbers are stored run-length encoded: one
+/// entry per change of source line rather than one per byte.
+#[derive(Debug)]
 pub struct Chunk {
-	pub lines: Vec<usize>,
+	lines: Vec<LineRun>,
 	pub code: Rc<RefCell<Vec<u8>>>,
-	pub constants: Vec<Rc<Value>>
+	pub constants: Vec<Value>
+}
+
+impl Default for Chunk {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Chunk {
@@ -42,19 +117,89 @@ impl Chunk {
     }
 
 	pub fn write(&mut self, byte: u8, line: usize) {
+		let offset = self.size();
 		self.code.borrow_mut().push(byte);
-		self.lines.push(line);
-		assert_eq!(self.lines.len(), self.code.borrow().len());
+
+		if self.lines.last().is_none_or(|run| run.line != line) {
+			self.lines.push(LineRun { offset, line });
+		}
 	}
 
-	pub fn add_constant(&mut self, value: Rc<Value>) -> usize {
+	/// The source line of the byte at `offset`, or `None` past the end of
+	/// the code.
+	pub fn line_at(&self, offset: usize) -> Option<usize> {
+		if offset >= self.size() {
+			return None;
+		}
+
+		let run = self.lines.partition_point(|run| run.offset <= offset);
+		self.lines.get(run.checked_sub(1)?).map(|run| run.line)
+	}
+
+	pub fn add_constant(&mut self, value: Value) -> usize {
 		self.constants.push(value);
 		self.constants.len() - 1
 	}
 
 	pub fn size(&self) -> usize {
-		assert_eq!(self.lines.len(), self.code.borrow().len());
-		self.lines.len()
+		self.code.borrow().len()
 	}
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A chunk with one byte per entry of `lines`.
+	fn chunk_with_lines(lines: &[usize]) -> Chunk {
+		let mut chunk = Chunk::new();
+		for line in lines {
+			chunk.write(0, *line);
+		}
+		chunk
+	}
+
+	#[test]
+	fn empty_chunk_has_no_lines() {
+		assert_eq!(Chunk::new().line_at(0), None);
+	}
+
+	#[test]
+	fn first_and_last_instruction() {
+		let chunk = chunk_with_lines(&[1, 1, 2, 3, 3, 7]);
+		assert_eq!(chunk.line_at(0), Some(1));
+		assert_eq!(chunk.line_at(5), Some(7));
+		assert_eq!(chunk.line_at(6), None);
+	}
+
+	#[test]
+	fn lookups_at_run_boundaries() {
+		let chunk = chunk_with_lines(&[1, 1, 1, 2, 2, 4, 5, 5]);
+		assert_eq!(chunk.lines.len(), 4);
+
+		let lines: Vec<_> = (0..chunk.size()).map(|offset| chunk.line_at(offset)).collect();
+		assert_eq!(lines, [1, 1, 1, 2, 2, 4, 5, 5].map(Some));
+	}
+
+	#[test]
+	fn one_line_spanning_many_bytes() {
+		let mut lines = vec![1; 1000];
+		lines.push(2);
+		let chunk = chunk_with_lines(&lines);
+
+		assert_eq!(chunk.lines.len(), 2);
+		assert_eq!(chunk.line_at(0), Some(1));
+		assert_eq!(chunk.line_at(500), Some(1));
+		assert_eq!(chunk.line_at(999), Some(1));
+		assert_eq!(chunk.line_at(1000), Some(2));
+		assert_eq!(chunk.line_at(1001), None);
+	}
+
+	#[test]
+	fn returning_to_an_earlier_line_starts_a_new_run() {
+		let chunk = chunk_with_lines(&[3, 4, 3]);
+		assert_eq!(chunk.lines.len(), 3);
+		assert_eq!(chunk.line_at(2), Some(3));
+	}
+}
@@ -1,30 +1,200 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashMap;
 
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
-use crate::{chunk::{Chunk, OpCode}, error::{CompilerError, RLoxError}, parser::Parser, scanner::Scanner, token::{Token, TokenType}, value::{Obj, Value}};
+use crate::{bigint::BigInt, chunk::{Chunk, OpCode}, error::{CompilerError, RLoxError}, memory::{GcRef, Heap}, parser::Parser, scanner::{self, Scanner}, token::{Token, TokenType}, value::{Function, Obj, Value}};
 
 #[derive(FromPrimitive)]
 enum Precedence {
   None,
   Assignment,  // =
+  Conditional, // ?:
   Or,          // or
   And,         // and
   Equality,    // == !=
   Comparison,  // < > <= >=
+  BitOr,       // |
+  BitXor,      // ^
+  BitAnd,      // &
+  Shift,       // << >>
   Term,        // + -
-  Factor,      // * /
-  Unary,       // ! -
+  Factor,      // * / % ~/
+  Unary,       // ! - ~
+  Exponent,    // **
   Call,        // . ()
   Primary
 }
 
+const LOCALS_MAX: usize = u8::MAX as usize + 1;
+const UPVALUES_MAX: usize = u8::MAX as usize + 1;
+const GLOBALS_MAX: usize = 1 << 24;
+const CONSTANTS_MAX: usize = 1 << 24;
+const JUMP_TABLE_MAX: usize = u8::MAX as usize + 1;
+
+/// Maps global variable names to the slots the VM stores them in. The table
+/// outlives a single compilation so that every script run on a VM agrees on
+/// the slots. A name gets its slot the first time it is compiled, even if
+/// it is only defined later, which keeps globals late bound.
+#[derive(Default)]
+pub struct GlobalNames {
+	slots: HashMap<GcRef, u32>,
+	names: Vec<GcRef>,
+}
+
+impl GlobalNames {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Returns the slot for the interned string `name`, assigning the next
+	/// free one if needed. Returns `None` once every slot is taken.
+	pub fn resolve(&mut self, name: GcRef) -> Option<u32> {
+		if let Some(slot) = self.slots.get(&name) {
+			return Some(*slot);
+		}
+
+		if self.names.len() == GLOBALS_MAX {
+			return None;
+		}
+
+		let slot = self.names.len() as u32;
+		self.slots.insert(name, slot);
+		self.names.push(name);
+		Some(slot)
+	}
+
+	/// The interned name of the global stored in `slot`.
+	pub fn name(&self, slot: u32) -> Option<GcRef> {
+		self.names.get(slot as usize).copied()
+	}
+
+	pub fn names(&self) -> &[GcRef] {
+		&self.names
+	}
+
+	pub fn len(&self) -> usize {
+		self.names.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.names.is_empty()
+	}
+}
+
+/// A local variable slot. `depth` is `None` while the variable's
+/// initializer is still being compiled.
+struct Local<'src> {
+	name: &'src str,
+	depth: Option<usize>,
+	is_captured: bool,
+}
+
+/// A loop or switch whose body is being compiled. `break` jumps are
+/// patched to the end of the loop once it is known; `continue` jumps back to
+/// `continue_target`, or forward to the condition of a do-while loop, which
+/// is only known after the body. A switch is only a target for `break`.
+struct LoopState<'src> {
+	label: Option<&'src str>,
+	is_switch: bool,
+	scope_depth: usize,
+	continue_target: Option<usize>,
+	continue_jumps: Vec<usize>,
+	break_jumps: Vec<usize>,
+}
+
+/// The value of a number literal, before it becomes a `Value`.
+enum NumberLiteral {
+	Int(i64),
+	Float(f64),
+}
+
+/// Something an assignment can store into: a variable, or the property
+/// `name` of the instance the preceding code left on the stack.
+#[derive(Clone, Copy)]
+enum Target {
+	Variable { get_op: OpCode, set_op: OpCode, slot: u32 },
+	Property { name: u32 },
+}
+
+/// A prefix `++` or `--` waiting for the end of its operand, which must be a
+/// target. `depth` is the `parse_precedence` nesting of the operand, so that
+/// targets inside it, such as call arguments, don't claim the increment.
+#[derive(Clone, Copy)]
+struct PendingIncrement {
+	operator: TokenType,
+	depth: usize,
+}
+
+/// A variable captured by a closure: either a local slot of the directly
+/// enclosing function (`is_local`) or one of that function's own upvalues.
+#[derive(Clone, Copy, PartialEq)]
+struct UpvalueSlot {
+	index: u8,
+	is_local: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+	Function,
+	Initializer,
+	Method,
+	Script,
+}
+
+/// Per-function compilation state. A new one is pushed for every nested
+/// function declaration and popped once its body has been compiled.
+struct FunctionState<'src> {
+	function: Function,
+	function_type: FunctionType,
+	locals: Vec<Local<'src>>,
+	upvalues: Vec<UpvalueSlot>,
+	loops: Vec<LoopState<'src>>,
+	/// The constant slots of the names already in the chunk, so that each
+	/// name is added once however often it is used.
+	names: HashMap<GcRef, u32>,
+	scope_depth: usize,
+}
+
+impl<'src> FunctionState<'src> {
+	fn new(function_type: FunctionType, name: Option<String>) -> Self {
+		let mut locals = Vec::with_capacity(LOCALS_MAX);
+
+		// Slot zero holds the function being called, or the receiver for
+		// methods, where it is reachable as `this`.
+		let slot_zero = match function_type {
+			FunctionType::Method | FunctionType::Initializer => "this",
+			FunctionType::Function | FunctionType::Script => "",
+		};
+		locals.push(Local { name: slot_zero, depth: Some(0), is_captured: false });
+
+		Self {
+			function: Function::new(name),
+			function_type,
+			locals,
+			upvalues: Vec::new(),
+			loops: Vec::new(),
+			names: HashMap::new(),
+			scope_depth: 0,
+		}
+	}
+}
+
+/// Tracks the class declarations enclosing the code being compiled.
+struct ClassState {
+	has_superclass: bool,
+}
+
 pub struct Compiler<'src> {
-	pub current_chunk: Rc<RefCell<Chunk>>,
+	heap: &'src mut Heap,
+	globals: &'src mut GlobalNames,
 	parser: Parser,
 	scanner: Scanner<'src>,
-	parse_rules: HashMap<TokenType, ParseRule>
+	parse_rules: HashMap<TokenType, ParseRule>,
+	states: Vec<FunctionState<'src>>,
+	classes: Vec<ClassState>,
+	increment: Option<PendingIncrement>,
+	depth: usize,
 }
 
 type ParseFn = fn(&mut Compiler, can_assign: bool) -> Result<(), RLoxError>;
@@ -62,20 +232,60 @@ fn variable_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<()
     c.variable(can_assign)
 }
 
+fn increment_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.increment(can_assign)
+}
+
+fn call_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.call(can_assign)
+}
+
+fn dot_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.dot(can_assign)
+}
+
+fn this_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.this(can_assign)
+}
+
+fn super_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.super_(can_assign)
+}
+
+fn conditional_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.conditional(can_assign)
+}
+
+fn and_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.and(can_assign)
+}
+
+fn or_wrapper<'src>(c: &mut Compiler<'src>, can_assign: bool) -> Result<(), RLoxError> {
+    c.or(can_assign)
+}
+
 impl<'src> Compiler<'src> {
-	pub fn new(source: &'src str) -> Self {
+	/// Objects created while compiling (strings, functions) are allocated
+	/// in `heap`. The compiler never triggers a collection itself. Global
+	/// variables are resolved to slots through `globals`.
+	pub fn new(source: &'src str, heap: &'src mut Heap, globals: &'src mut GlobalNames) -> Self {
         let mut rules = HashMap::new();
-        rules.insert(TokenType::LeftParen,   ParseRule { prefix: Some(grouping_wrapper), infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::LeftParen,   ParseRule { prefix: Some(grouping_wrapper), infix: Some(call_wrapper), precedence: Precedence::Call as u8 });
         rules.insert(TokenType::RightParen,  ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::LeftBrace,   ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::RightBrace,  ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Comma,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
-        rules.insert(TokenType::Dot,         ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Colon,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Question,    ParseRule { prefix: None, infix: Some(conditional_wrapper), precedence: Precedence::Conditional as u8 });
+        rules.insert(TokenType::Dot,         ParseRule { prefix: None, infix: Some(dot_wrapper), precedence: Precedence::Call as u8 });
         rules.insert(TokenType::Minus,       ParseRule { prefix: Some(unary_wrapper), infix: Some(binary_wrapper), precedence: Precedence::Term as u8 });
         rules.insert(TokenType::Plus,        ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Term as u8 });
         rules.insert(TokenType::SemiColon,   ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Slash,       ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Factor as u8 });
         rules.insert(TokenType::Star,        ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Factor as u8 });
+        rules.insert(TokenType::Percent,     ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Factor as u8 });
+        rules.insert(TokenType::TildeSlash,  ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Factor as u8 });
+        rules.insert(TokenType::StarStar,    ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Exponent as u8 });
         rules.insert(TokenType::Bang,        ParseRule { prefix: Some(unary_wrapper), infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::BangEqual,   ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Equality as u8 });
         rules.insert(TokenType::Equal,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
@@ -84,36 +294,62 @@ impl<'src> Compiler<'src> {
         rules.insert(TokenType::GreaterEqual,ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Comparison as u8 });
         rules.insert(TokenType::Less,        ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Comparison as u8 });
         rules.insert(TokenType::LessEqual,   ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Comparison as u8 });
+        rules.insert(TokenType::Pipe,        ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::BitOr as u8 });
+        rules.insert(TokenType::Caret,       ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::BitXor as u8 });
+        rules.insert(TokenType::Ampersand,   ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::BitAnd as u8 });
+        rules.insert(TokenType::LessLess,    ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Shift as u8 });
+        rules.insert(TokenType::GreaterGreater, ParseRule { prefix: None, infix: Some(binary_wrapper), precedence: Precedence::Shift as u8 });
+        rules.insert(TokenType::Tilde,       ParseRule { prefix: Some(unary_wrapper), infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::PlusEqual,   ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::MinusEqual,  ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::StarEqual,   ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::SlashEqual,  ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::PercentEqual,ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::PlusPlus,    ParseRule { prefix: Some(increment_wrapper), infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::MinusMinus,  ParseRule { prefix: Some(increment_wrapper), infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Identifier,  ParseRule { prefix: Some(variable_wrapper), infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::String,      ParseRule { prefix: Some(string_wrapper), infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Number,      ParseRule { prefix: Some(number_wrapper), infix: None, precedence: Precedence::None as u8 });
-        rules.insert(TokenType::And,         ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::And,         ParseRule { prefix: None, infix: Some(and_wrapper), precedence: Precedence::And as u8 });
+        rules.insert(TokenType::Break,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Case,        ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Class,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Continue,    ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Default,     ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Do,          ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Else,        ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::False,       ParseRule { prefix: Some(literal_wrapper), infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::For,         ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Fun,         ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::If,          ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Nil,         ParseRule { prefix: Some(literal_wrapper), infix: None, precedence: Precedence::None as u8 });
-        rules.insert(TokenType::Or,          ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Or,          ParseRule { prefix: None, infix: Some(or_wrapper), precedence: Precedence::Or as u8 });
         rules.insert(TokenType::Print,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Return,      ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
-        rules.insert(TokenType::Super,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
-        rules.insert(TokenType::This,        ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Switch,      ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::Super,       ParseRule { prefix: Some(super_wrapper), infix: None, precedence: Precedence::None as u8 });
+        rules.insert(TokenType::This,        ParseRule { prefix: Some(this_wrapper), infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::True,        ParseRule { prefix: Some(literal_wrapper), infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::Var,         ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::While,       ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
         rules.insert(TokenType::EOF,         ParseRule { prefix: None, infix: None, precedence: Precedence::None as u8 });
 
         Self {
-            current_chunk: Rc::new(RefCell::new(Chunk::new())),
+            heap,
+            globals,
             parser: Parser::new(),
             scanner: Scanner::new(source),
             parse_rules: rules,
+            states: vec![FunctionState::new(FunctionType::Script, None)],
+            classes: Vec::new(),
+            increment: None,
+            depth: 0,
         }
 	}
 
-	pub fn compile(&mut self) -> Result<bool, RLoxError> {
+	/// Compiles the whole source into the top-level script function.
+	/// Returns `None` if any compile errors were reported.
+	pub fn compile(&mut self) -> Result<Option<Function>, RLoxError> {
 		self.parser.panic_mode = false;
 		self.parser.had_error = false;
 
@@ -122,12 +358,32 @@ impl<'src> Compiler<'src> {
 			self.declaration()?;
 		}
 
-		self.end()?;
-		Ok(!self.parser.had_error)
+		let (function, _) = self.end()?;
+		if self.parser.had_error {
+			return Ok(None);
+		}
+
+		Ok(Some(function))
+	}
+
+	fn state(&self) -> &FunctionState<'src> {
+		self.states.last().expect("Compiler has no function state")
+	}
+
+	fn state_mut(&mut self) -> &mut FunctionState<'src> {
+		self.states.last_mut().expect("Compiler has no function state")
+	}
+
+	fn current_chunk(&mut self) -> &mut Chunk {
+		&mut self.state_mut().function.chunk
 	}
 
 	fn declaration(&mut self) -> Result<(), RLoxError> {
-		if self.match_token(TokenType::Var)? {
+		if self.match_token(TokenType::Class)? {
+			self.class_declaration()?;
+		} else if self.match_token(TokenType::Fun)? {
+			self.fun_declaration()?;
+		} else if self.match_token(TokenType::Var)? {
 			self.var_declaration()?;
 		} else {
 			self.statement()?;
@@ -140,6 +396,113 @@ impl<'src> Compiler<'src> {
 		Ok(())
 	}
 
+	fn class_declaration(&mut self) -> Result<(), RLoxError> {
+		let global = self.parse_variable("Expect class name.")?;
+		let class_name = self.prev()?.slice(self.scanner.source);
+		let name_constant = self.name_constant(class_name);
+
+		self.emit_indexed(OpCode::OpClass, OpCode::OpClassLong, name_constant)?;
+		self.define_variable(global)?;
+
+		self.classes.push(ClassState { has_superclass: false });
+
+		if self.match_token(TokenType::Less)? {
+			self.consume(TokenType::Identifier, "Expect superclass name.")?;
+			self.variable(false)?;
+
+			if self.prev()?.slice(self.scanner.source) == class_name {
+				self.error("A class can't inherit from itself.");
+			}
+
+			// The superclass lives in a local named `super` so that methods
+			// can capture it.
+			self.begin_scope();
+			self.add_local("super");
+			self.define_variable(0)?;
+
+			self.load_variable(class_name)?;
+			self.emit_byte(OpCode::OpInherit as u8)?;
+
+			if let Some(class) = self.classes.last_mut() {
+				class.has_superclass = true;
+			}
+		}
+
+		// Load the class so the method definitions can attach to it.
+		self.load_variable(class_name)?;
+		self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+		while !self.check(TokenType::RightBrace)? && !self.check(TokenType::EOF)? {
+			self.method()?;
+		}
+		self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+		self.emit_byte(OpCode::OpPop as u8)?;
+
+		if self.classes.pop().is_some_and(|class| class.has_superclass) {
+			self.end_scope()?;
+		}
+
+		Ok(())
+	}
+
+	fn method(&mut self) -> Result<(), RLoxError> {
+		self.consume(TokenType::Identifier, "Expect method name.")?;
+		let name = self.prev()?;
+		let constant = self.identifier_constant(&name);
+
+		let function_type = if name.slice(self.scanner.source) == "init" {
+			FunctionType::Initializer
+		} else {
+			FunctionType::Method
+		};
+
+		self.function(function_type)?;
+		self.emit_indexed(OpCode::OpMethod, OpCode::OpMethodLong, constant)
+	}
+
+	fn fun_declaration(&mut self) -> Result<(), RLoxError> {
+		let global = self.parse_variable("Expect function name.")?;
+		self.mark_initialized();
+		self.function(FunctionType::Function)?;
+		self.define_variable(global)
+	}
+
+	fn function(&mut self, function_type: FunctionType) -> Result<(), RLoxError> {
+		let name = self.prev()?.slice(self.scanner.source).to_string();
+		self.states.push(FunctionState::new(function_type, Some(name)));
+		self.begin_scope();
+
+		self.consume(TokenType::LeftParen, "Expect '(' after function name.")?;
+		if !self.check(TokenType::RightParen)? {
+			loop {
+				self.state_mut().function.arity += 1;
+				if self.state().function.arity > u8::MAX as usize {
+					self.error_at_current("Can't have more than 255 parameters.");
+				}
+
+				let parameter = self.parse_variable("Expect parameter name.")?;
+				self.define_variable(parameter)?;
+
+				if !self.match_token(TokenType::Comma)? {
+					break;
+				}
+			}
+		}
+		self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+		self.consume(TokenType::LeftBrace, "Expect '{' before function body.")?;
+		self.block()?;
+
+		let (function, upvalues) = self.end()?;
+		let function = self.heap.alloc(Obj::Function(function));
+		let constant = self.make_constant(Value::obj(function));
+		self.emit_indexed(OpCode::OpClosure, OpCode::OpClosureLong, constant)?;
+
+		for upvalue in upvalues {
+			self.emit_bytes(upvalue.is_local as u8, upvalue.index)?;
+		}
+
+		Ok(())
+	}
+
 	fn var_declaration(&mut self) -> Result<(), RLoxError> {
 		let global = self.parse_variable("Expect variable name.")?;
 
@@ -154,31 +517,299 @@ impl<'src> Compiler<'src> {
 	}
 
 	fn variable(&mut self, can_assign: bool) -> Result<(), RLoxError> {
-		self.named_variable(&self.prev()?, can_assign)
+		self.named_variable(self.prev()?.slice(self.scanner.source), can_assign)
+	}
+
+	fn super_(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		match self.classes.last() {
+			None => self.error("Can't use 'super' outside of a class."),
+			Some(class) if !class.has_superclass => self.error("Can't use 'super' in a class with no superclass."),
+			Some(_) => {}
+		}
+
+		self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+		self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+		let name = self.identifier_constant(&self.prev()?);
+
+		self.load_variable("this")?;
+		if self.match_token(TokenType::LeftParen)? {
+			let arg_count = self.argument_list()?;
+			self.load_variable("super")?;
+			self.emit_indexed(OpCode::OpSuperInvoke, OpCode::OpSuperInvokeLong, name)?;
+			self.emit_byte(arg_count)
+		} else {
+			self.load_variable("super")?;
+			self.emit_indexed(OpCode::OpGetSuper, OpCode::OpGetSuperLong, name)
+		}
+	}
+
+	fn this(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		if self.classes.is_empty() {
+			self.error("Can't use 'this' outside of a class.");
+			return Ok(());
+		}
+
+		self.load_variable("this")
+	}
+
+	fn named_variable(&mut self, name: &'src str, can_assign: bool) -> Result<(), RLoxError> {
+		let target = self.resolve_variable(name);
+		self.target(target, can_assign)
+	}
+
+	/// Reads a variable the language itself refers to, which is never the
+	/// target of an assignment.
+	fn load_variable(&mut self, name: &'src str) -> Result<(), RLoxError> {
+		let target = self.resolve_variable(name);
+		self.emit_get(target)
 	}
 
-	fn named_variable(&mut self, token: &Token, can_assign: bool) -> Result<(), RLoxError> {
-		let arg = self.identifier_constant(&token);
+	fn resolve_variable(&mut self, name: &'src str) -> Target {
+		let current = self.states.len() - 1;
 
+		if let Some(slot) = self.resolve_local(current, name) {
+			Target::Variable { get_op: OpCode::OpGetLocal, set_op: OpCode::OpSetLocal, slot: slot as u32 }
+		} else if let Some(slot) = self.resolve_upvalue(current, name) {
+			Target::Variable { get_op: OpCode::OpGetUpvalue, set_op: OpCode::OpSetUpvalue, slot: slot as u32 }
+		} else {
+			Target::Variable { get_op: OpCode::OpGetGlobal, set_op: OpCode::OpSetGlobal, slot: self.global_slot(name) }
+		}
+	}
+
+	/// Compiles a use of `target`: a read, or one of the assignment and
+	/// increment forms. Each form reads and writes the target through
+	/// stack operations, so the expression producing a property's instance
+	/// is evaluated only once.
+	fn target(&mut self, target: Target, can_assign: bool) -> Result<(), RLoxError> {
 		if can_assign && self.match_token(TokenType::Equal)? {
 			self.expression()?;
-			self.emit_bytes(OpCode::OpSetGlobal as u8, arg)
-		} else {
-			self.emit_bytes(OpCode::OpGetGlobal as u8, arg)
+			return self.emit_set(target);
+		}
+
+		if can_assign && let Some(op) = self.match_compound_assignment()? {
+			self.emit_get_for_update(target)?;
+			self.expression()?;
+			self.emit_byte(op as u8)?;
+			return self.emit_set(target);
+		}
+
+		if self.match_token(TokenType::PlusPlus)? || self.match_token(TokenType::MinusMinus)? {
+			// Postfix: keep the old value below what the store needs.
+			let op = Self::increment_op(self.prev()?.token_type);
+			self.emit_get_for_update(target)?;
+			match target {
+				Target::Variable { .. } => self.emit_byte(OpCode::OpDup as u8)?,
+				Target::Property { .. } => self.emit_bytes(OpCode::OpSwap as u8, OpCode::OpOver as u8)?,
+			}
+			self.emit_constant(Value::int(1))?;
+			self.emit_byte(op as u8)?;
+			self.emit_set(target)?;
+			return self.emit_byte(OpCode::OpPop as u8);
+		}
+
+		if let Some(operator) = self.take_increment()? {
+			let op = Self::increment_op(operator);
+			self.emit_get_for_update(target)?;
+			self.emit_constant(Value::int(1))?;
+			self.emit_byte(op as u8)?;
+			return self.emit_set(target);
+		}
+
+		self.emit_get(target)
+	}
+
+	fn emit_get(&mut self, target: Target) -> Result<(), RLoxError> {
+		match target {
+			Target::Variable { get_op, slot, .. } => self.emit_variable_op(get_op, slot),
+			Target::Property { name } => self.emit_indexed(OpCode::OpGetProperty, OpCode::OpGetPropertyLong, name),
+		}
+	}
+
+	/// Reads `target` ahead of storing into it, keeping the instance of a
+	/// property on the stack for the store.
+	fn emit_get_for_update(&mut self, target: Target) -> Result<(), RLoxError> {
+		if let Target::Property { .. } = target {
+			self.emit_byte(OpCode::OpDup as u8)?;
+		}
+		self.emit_get(target)
+	}
+
+	fn emit_set(&mut self, target: Target) -> Result<(), RLoxError> {
+		match target {
+			Target::Variable { set_op, slot, .. } => self.emit_variable_op(set_op, slot),
+			Target::Property { name } => self.emit_indexed(OpCode::OpSetProperty, OpCode::OpSetPropertyLong, name),
+		}
+	}
+
+	/// Consumes a compound assignment operator such as `+=`, returning the
+	/// instruction that combines the old value with the right-hand side.
+	fn match_compound_assignment(&mut self) -> Result<Option<OpCode>, RLoxError> {
+		let op = match self.curr()?.token_type {
+			TokenType::PlusEqual => OpCode::OpAdd,
+			TokenType::MinusEqual => OpCode::OpSubtract,
+			TokenType::StarEqual => OpCode::OpMultiply,
+			TokenType::SlashEqual => OpCode::OpDivide,
+			TokenType::PercentEqual => OpCode::OpModulo,
+			_ => return Ok(None),
+		};
+
+		self.advance()?;
+		Ok(Some(op))
+	}
+
+	fn increment_op(operator: TokenType) -> OpCode {
+		match operator {
+			TokenType::PlusPlus => OpCode::OpAdd,
+			_ => OpCode::OpSubtract,
 		}
 	}
 
-	fn parse_variable(&mut self, message: &str) -> Result<u8, RLoxError> {
+	/// Claims the pending prefix increment if the target being compiled
+	/// ends its operand, i.e. it is not followed by a property access or a
+	/// call.
+	fn take_increment(&mut self) -> Result<Option<TokenType>, RLoxError> {
+		let ends_operand = !self.check(TokenType::Dot)? && !self.check(TokenType::LeftParen)?;
+
+		match self.increment {
+			Some(pending) if pending.depth == self.depth && ends_operand => {
+				self.increment = None;
+				Ok(Some(pending.operator))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	/// Looks `name` up among the locals of the function at `state` in the
+	/// compiler stack.
+	fn resolve_local(&mut self, state: usize, name: &str) -> Option<u8> {
+		let (slot, initialized) = self.states[state].locals.iter().enumerate().rev()
+			.find(|(_, local)| local.name == name)
+			.map(|(slot, local)| (slot, local.depth.is_some()))?;
+
+		if !initialized {
+			self.error("Can't read local variable in its own initializer.");
+		}
+
+		Some(slot as u8)
+	}
+
+	/// Resolves `name` as a variable captured from an enclosing function,
+	/// threading the upvalue through every function in between.
+	fn resolve_upvalue(&mut self, state: usize, name: &str) -> Option<u8> {
+		if state == 0 {
+			return None;
+		}
+
+		let enclosing = state - 1;
+		if let Some(local) = self.resolve_local(enclosing, name) {
+			self.states[enclosing].locals[local as usize].is_captured = true;
+			return Some(self.add_upvalue(state, local, true));
+		}
+
+		let upvalue = self.resolve_upvalue(enclosing, name)?;
+		Some(self.add_upvalue(state, upvalue, false))
+	}
+
+	fn add_upvalue(&mut self, state: usize, index: u8, is_local: bool) -> u8 {
+		let upvalue = UpvalueSlot { index, is_local };
+		if let Some(existing) = self.states[state].upvalues.iter().position(|u| *u == upvalue) {
+			return existing as u8;
+		}
+
+		if self.states[state].upvalues.len() == UPVALUES_MAX {
+			self.error("Too many closure variables in function.");
+			return 0;
+		}
+
+		let function_state = &mut self.states[state];
+		function_state.upvalues.push(upvalue);
+		function_state.function.upvalue_count = function_state.upvalues.len();
+		(function_state.upvalues.len() - 1) as u8
+	}
+
+	fn parse_variable(&mut self, message: &str) -> Result<u32, RLoxError> {
 		self.consume(TokenType::Identifier, message)?;
-		Ok(self.identifier_constant(&self.prev()?))
+
+		self.declare_variable()?;
+		if self.state().scope_depth > 0 {
+			return Ok(0);
+		}
+
+		Ok(self.global_slot(self.prev()?.slice(self.scanner.source)))
+	}
+
+	fn global_slot(&mut self, name: &str) -> u32 {
+		let name = self.heap.intern(name);
+		match self.globals.resolve(name) {
+			Some(slot) => slot,
+			None => {
+				self.error("Too many global variables.");
+				0
+			}
+		}
+	}
+
+	fn declare_variable(&mut self) -> Result<(), RLoxError> {
+		let scope_depth = self.state().scope_depth;
+		if scope_depth == 0 {
+			return Ok(());
+		}
+
+		let name = self.prev()?.slice(self.scanner.source);
+		let already_declared = self.state().locals.iter().rev()
+			.take_while(|local| local.depth.is_none_or(|depth| depth >= scope_depth))
+			.any(|local| local.name == name);
+
+		if already_declared {
+			self.error("Already a variable with this name in this scope.");
+		}
+
+		self.add_local(name);
+		Ok(())
 	}
 
-	fn define_variable(&mut self, global: u8) -> Result<(), RLoxError> {
-		self.emit_bytes(OpCode::OpDefineGlobal as u8, global)
+	fn add_local(&mut self, name: &'src str) {
+		if self.state().locals.len() == LOCALS_MAX {
+			self.error("Too many local variables in function.");
+			return;
+		}
+
+		self.state_mut().locals.push(Local { name, depth: None, is_captured: false });
 	}
 
-	fn identifier_constant(&mut self, name: &Token) -> u8 {
-		self.make_constant(Value::obj(Obj::String(self.copy_string(name.start, name.length))))
+	fn mark_initialized(&mut self) {
+		let state = self.state_mut();
+		if state.scope_depth == 0 {
+			return;
+		}
+
+		if let Some(local) = state.locals.last_mut() {
+			local.depth = Some(state.scope_depth);
+		}
+	}
+
+	fn define_variable(&mut self, global: u32) -> Result<(), RLoxError> {
+		if self.state().scope_depth > 0 {
+			self.mark_initialized();
+			return Ok(());
+		}
+
+		self.emit_variable_op(OpCode::OpDefineGlobal, global)
+	}
+
+	fn identifier_constant(&mut self, name: &Token) -> u32 {
+		self.name_constant(name.slice(self.scanner.source))
+	}
+
+	fn name_constant(&mut self, name: &str) -> u32 {
+		let name = self.heap.intern(name);
+		if let Some(constant) = self.state().names.get(&name) {
+			return *constant;
+		}
+
+		let constant = self.make_constant(Value::obj(name));
+		self.state_mut().names.insert(name, constant);
+		constant
 	}
 
 	fn synchronize(&mut self) -> Result<(), RLoxError> {
@@ -192,6 +823,7 @@ impl<'src> Compiler<'src> {
 			match self.prev()?.token_type {
 				TokenType::Class | TokenType::Fun | TokenType::Var | 
 				TokenType::For | TokenType::If | TokenType::While |
+				TokenType::Do | TokenType::Break | TokenType::Continue | TokenType::Switch |
 				TokenType::Print | TokenType::Return => {
 					return Ok(());
 				}
@@ -208,13 +840,538 @@ impl<'src> Compiler<'src> {
 	fn statement(&mut self) -> Result<(), RLoxError> {
 		if self.match_token(TokenType::Print)? {
 			self.print_statement()?;
+		} else if self.match_token(TokenType::Return)? {
+			self.return_statement()?;
+		} else if self.match_token(TokenType::If)? {
+			self.if_statement()?;
+		} else if self.match_token(TokenType::While)? {
+			self.while_statement(None)?;
+		} else if self.match_token(TokenType::For)? {
+			self.for_statement(None)?;
+		} else if self.match_token(TokenType::Do)? {
+			self.do_while_statement(None)?;
+		} else if self.match_token(TokenType::Switch)? {
+			self.switch_statement()?;
+		} else if self.match_token(TokenType::Break)? {
+			self.break_statement()?;
+		} else if self.match_token(TokenType::Continue)? {
+			self.continue_statement()?;
+		} else if self.check(TokenType::Identifier)? && self.peek_next()?.token_type == TokenType::Colon {
+			self.labeled_statement()?;
+		} else if self.match_token(TokenType::LeftBrace)? {
+			self.begin_scope();
+			self.block()?;
+			self.end_scope()?;
+		} else {
+			self.expression_statement()?;
+		}
+
+		Ok(())
+	}
+
+	fn if_statement(&mut self) -> Result<(), RLoxError> {
+		self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+		self.expression()?;
+		self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+
+		let then_jump = self.emit_jump(OpCode::OpJumpIfFalse)?;
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.statement()?;
+
+		let else_jump = self.emit_jump(OpCode::OpJump)?;
+		self.patch_jump(then_jump);
+		self.emit_byte(OpCode::OpPop as u8)?;
+
+		if self.match_token(TokenType::Else)? {
+			self.statement()?;
+		}
+
+		self.patch_jump(else_jump);
+		Ok(())
+	}
+
+	fn labeled_statement(&mut self) -> Result<(), RLoxError> {
+		self.advance()?;
+		let label = self.prev()?.slice(self.scanner.source);
+		self.consume(TokenType::Colon, "Expect ':' after label.")?;
+
+		if self.match_token(TokenType::While)? {
+			self.while_statement(Some(label))
+		} else if self.match_token(TokenType::For)? {
+			self.for_statement(Some(label))
+		} else if self.match_token(TokenType::Do)? {
+			self.do_while_statement(Some(label))
+		} else {
+			self.error_at_current("Expect loop after label.");
+			Ok(())
+		}
+	}
+
+	fn while_statement(&mut self, label: Option<&'src str>) -> Result<(), RLoxError> {
+		let loop_start = self.current_chunk().size();
+
+		self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+		self.expression()?;
+		self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+
+		let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse)?;
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.begin_loop(label, Some(loop_start));
+		self.statement()?;
+		self.emit_loop(loop_start)?;
+
+		self.patch_jump(exit_jump);
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.end_loop();
+		Ok(())
+	}
+
+	fn do_while_statement(&mut self, label: Option<&'src str>) -> Result<(), RLoxError> {
+		let loop_start = self.current_chunk().size();
+
+		self.begin_loop(label, None);
+		self.statement()?;
+		self.consume(TokenType::While, "Expect 'while' after loop body.")?;
+
+		// `continue` jumps straight to the condition.
+		let continue_jumps = self.state_mut().loops.last_mut()
+			.map(|current| std::mem::take(&mut current.continue_jumps))
+			.unwrap_or_default();
+		for jump in continue_jumps {
+			self.patch_jump(jump);
+		}
+
+		self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+		self.expression()?;
+		self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+		self.consume(TokenType::SemiColon, "Expect ';' after do-while condition.")?;
+
+		let exit_jump = self.emit_jump(OpCode::OpJumpIfFalse)?;
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.emit_loop(loop_start)?;
+
+		self.patch_jump(exit_jump);
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.end_loop();
+		Ok(())
+	}
+
+	fn for_statement(&mut self, label: Option<&'src str>) -> Result<(), RLoxError> {
+		self.begin_scope();
+		self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+		if self.match_token(TokenType::SemiColon)? {
+			// No initializer.
+		} else if self.match_token(TokenType::Var)? {
+			self.var_declaration()?;
 		} else {
 			self.expression_statement()?;
 		}
 
+		let mut loop_start = self.current_chunk().size();
+		let mut exit_jump = None;
+
+		if !self.match_token(TokenType::SemiColon)? {
+			self.expression()?;
+			self.consume(TokenType::SemiColon, "Expect ';' after loop condition.")?;
+
+			exit_jump = Some(self.emit_jump(OpCode::OpJumpIfFalse)?);
+			self.emit_byte(OpCode::OpPop as u8)?;
+		}
+
+		if !self.match_token(TokenType::RightParen)? {
+			let body_jump = self.emit_jump(OpCode::OpJump)?;
+			let increment_start = self.current_chunk().size();
+
+			self.expression()?;
+			self.emit_byte(OpCode::OpPop as u8)?;
+			self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+			self.emit_loop(loop_start)?;
+			loop_start = increment_start;
+			self.patch_jump(body_jump);
+		}
+
+		self.begin_loop(label, Some(loop_start));
+		self.statement()?;
+		self.emit_loop(loop_start)?;
+
+		if let Some(exit_jump) = exit_jump {
+			self.patch_jump(exit_jump);
+			self.emit_byte(OpCode::OpPop as u8)?;
+		}
+
+		self.end_loop();
+		self.end_scope()
+	}
+
+	/// Compiles a switch. Cases don't fall through. As in C, an unlabeled
+	/// `break` in a case leaves the switch; `continue` and labeled `break`
+	/// refer to the enclosing loops.
+	fn switch_statement(&mut self) -> Result<(), RLoxError> {
+		self.consume(TokenType::LeftParen, "Expect '(' after 'switch'.")?;
+		self.expression()?;
+		self.consume(TokenType::RightParen, "Expect ')' after value.")?;
+
+		// A `break` pops the hidden local too, so it jumps past the end of
+		// its scope.
+		self.begin_switch();
+
+		// The value being switched on stays in a hidden local while the cases
+		// run. Its name is a keyword, so code in the cases can't refer to it.
+		self.begin_scope();
+		self.add_local("switch");
+		self.mark_initialized();
+		let subject = (self.state().locals.len() - 1) as u8;
+
+		self.consume(TokenType::LeftBrace, "Expect '{' before switch cases.")?;
+		match self.jump_table_range()? {
+			Some((min, len)) => self.switch_jump_table(subject, min, len)?,
+			None => self.switch_chain(subject)?,
+		}
+		self.consume(TokenType::RightBrace, "Expect '}' after switch cases.")?;
+
+		self.end_scope()?;
+		self.end_loop();
+		Ok(())
+	}
+
+	/// Compiles the cases as a chain of equality tests, tried in order.
+	fn switch_chain(&mut self, subject: u8) -> Result<(), RLoxError> {
+		let mut end_jumps = Vec::new();
+		let mut seen_default = false;
+
+		while !self.check(TokenType::RightBrace)? && !self.check(TokenType::EOF)? {
+			if self.match_token(TokenType::Case)? {
+				if seen_default {
+					self.error("Can't have a case after the default case.");
+				}
+
+				let mut body_jumps = Vec::new();
+				loop {
+					self.emit_bytes(OpCode::OpGetLocal as u8, subject)?;
+					self.expression()?;
+					self.emit_byte(OpCode::OpEqual as u8)?;
+
+					let next_value = self.emit_jump(OpCode::OpJumpIfFalse)?;
+					self.emit_byte(OpCode::OpPop as u8)?;
+					body_jumps.push(self.emit_jump(OpCode::OpJump)?);
+					self.patch_jump(next_value);
+					self.emit_byte(OpCode::OpPop as u8)?;
+
+					if !self.match_token(TokenType::Comma)? {
+						break;
+					}
+				}
+				self.consume(TokenType::Colon, "Expect ':' after case value.")?;
+
+				let next_case = self.emit_jump(OpCode::OpJump)?;
+				for jump in body_jumps {
+					self.patch_jump(jump);
+				}
+
+				self.case_body()?;
+				end_jumps.push(self.emit_jump(OpCode::OpJump)?);
+				self.patch_jump(next_case);
+			} else if self.match_token(TokenType::Default)? {
+				self.default_case(&mut seen_default)?;
+			} else {
+				self.error_at_current("Expect 'case' or 'default'.");
+				self.advance()?;
+			}
+		}
+
+		for jump in end_jumps {
+			self.patch_jump(jump);
+		}
+
 		Ok(())
 	}
 
+	/// Compiles the cases behind an `OpJumpTable` indexed by the case values,
+	/// which `jump_table_range` has checked are integers in
+	/// `min..min + len`.
+	fn switch_jump_table(&mut self, subject: u8, min: i16, len: usize) -> Result<(), RLoxError> {
+		self.emit_bytes(OpCode::OpGetLocal as u8, subject)?;
+		self.emit_byte(OpCode::OpJumpTable as u8)?;
+		self.emit_bytes((min >> 8) as u8, min as u8)?;
+		self.emit_bytes((len >> 8) as u8, len as u8)?;
+
+		// One entry per value plus one for the default, patched below.
+		let table_start = self.current_chunk().size();
+		for _ in 0..=len {
+			self.emit_bytes(0xff, 0xff)?;
+		}
+		let table_end = self.current_chunk().size();
+
+		let mut targets = vec![None; len];
+		let mut default_target = None;
+		let mut end_jumps = Vec::new();
+		let mut seen_default = false;
+
+		while !self.check(TokenType::RightBrace)? && !self.check(TokenType::EOF)? {
+			if self.match_token(TokenType::Case)? {
+				if seen_default {
+					self.error("Can't have a case after the default case.");
+				}
+
+				let mut values = Vec::new();
+				loop {
+					let negative = self.match_token(TokenType::Minus)?;
+					self.consume(TokenType::Number, "Expect case value.")?;
+					// `jump_table_range` has checked that every value is a
+					// small integer.
+					let value = self.number_literal()?.as_int().unwrap_or_default();
+					values.push(if negative { -value } else { value });
+
+					if !self.match_token(TokenType::Comma)? {
+						break;
+					}
+				}
+				self.consume(TokenType::Colon, "Expect ':' after case value.")?;
+
+				// Like the chain, the first case with a value wins.
+				let body = self.current_chunk().size();
+				for value in values {
+					if let Some(target) = targets.get_mut((value - min as i64) as usize) {
+						target.get_or_insert(body);
+					}
+				}
+
+				self.case_body()?;
+				end_jumps.push(self.emit_jump(OpCode::OpJump)?);
+			} else if self.match_token(TokenType::Default)? {
+				default_target = Some(self.current_chunk().size());
+				self.default_case(&mut seen_default)?;
+			} else {
+				self.error_at_current("Expect 'case' or 'default'.");
+				self.advance()?;
+			}
+		}
+
+		let end = self.current_chunk().size();
+		for jump in end_jumps {
+			self.patch_jump(jump);
+		}
+
+		let default_target = default_target.unwrap_or(end);
+		for (index, target) in targets.into_iter().enumerate() {
+			self.write_jump(table_start + index * 2, target.unwrap_or(default_target) - table_end);
+		}
+		self.write_jump(table_start + len * 2, default_target - table_end);
+
+		Ok(())
+	}
+
+	fn default_case(&mut self, seen_default: &mut bool) -> Result<(), RLoxError> {
+		if *seen_default {
+			self.error("Can't have more than one default case.");
+		}
+		*seen_default = true;
+
+		self.consume(TokenType::Colon, "Expect ':' after 'default'.")?;
+		self.case_body()
+	}
+
+	/// Compiles the statements of one case, up to the next case or the end
+	/// of the switch. Each case gets its own scope.
+	fn case_body(&mut self) -> Result<(), RLoxError> {
+		self.begin_scope();
+		while !self.check(TokenType::Case)? && !self.check(TokenType::Default)?
+			&& !self.check(TokenType::RightBrace)? && !self.check(TokenType::EOF)? {
+			self.declaration()?;
+		}
+		self.end_scope()
+	}
+
+	/// Scans ahead through the switch body, starting at the current token,
+	/// and decides whether it can be compiled to a jump table: every case
+	/// value must be an integer literal and together they must densely cover
+	/// a range of at most `JUMP_TABLE_MAX` values. Returns the start and
+	/// length of that range.
+	fn jump_table_range(&self) -> Result<Option<(i16, usize)>, RLoxError> {
+		let mut scanner = self.scanner.clone();
+		let mut token = self.curr()?;
+		let mut depth = 0usize;
+		let mut values = Vec::new();
+
+		loop {
+			match token.token_type {
+				TokenType::EOF => return Ok(None),
+				TokenType::LeftBrace => depth += 1,
+				TokenType::RightBrace if depth == 0 => break,
+				TokenType::RightBrace => depth -= 1,
+				TokenType::Case if depth == 0 => loop {
+					let mut next = scanner.scan_token()?;
+					let negative = next.token_type == TokenType::Minus;
+					if negative {
+						next = scanner.scan_token()?;
+					}
+
+					let value = match next.token_type {
+						TokenType::Number => match Self::parse_number(next.slice(self.scanner.source)) {
+							Ok(NumberLiteral::Int(n)) => Some(n),
+							_ => None,
+						},
+						_ => None,
+					};
+					match value {
+						Some(value) if value.abs() <= i16::MAX as i64 => {
+							values.push(if negative { -value } else { value });
+						}
+						_ => return Ok(None),
+					}
+
+					match scanner.scan_token()?.token_type {
+						TokenType::Comma => continue,
+						TokenType::Colon => break,
+						_ => return Ok(None),
+					}
+				},
+				_ => {}
+			}
+
+			token = scanner.scan_token()?;
+		}
+
+		values.sort_unstable();
+		values.dedup();
+		let (Some(&min), Some(&max)) = (values.first(), values.last()) else {
+			return Ok(None);
+		};
+
+		let len = (max - min + 1) as usize;
+		if values.len() < 2 || len > JUMP_TABLE_MAX || len > values.len() * 2 {
+			return Ok(None);
+		}
+
+		Ok(Some((min as i16, len)))
+	}
+
+	fn break_statement(&mut self) -> Result<(), RLoxError> {
+		let Some(index) = self.loop_target("break")? else {
+			return Ok(());
+		};
+
+		self.discard_locals(self.state().loops[index].scope_depth)?;
+		let jump = self.emit_jump(OpCode::OpJump)?;
+		self.state_mut().loops[index].break_jumps.push(jump);
+		Ok(())
+	}
+
+	fn continue_statement(&mut self) -> Result<(), RLoxError> {
+		let Some(index) = self.loop_target("continue")? else {
+			return Ok(());
+		};
+
+		self.discard_locals(self.state().loops[index].scope_depth)?;
+		match self.state().loops[index].continue_target {
+			Some(target) => self.emit_loop(target),
+			None => {
+				let jump = self.emit_jump(OpCode::OpJump)?;
+				self.state_mut().loops[index].continue_jumps.push(jump);
+				Ok(())
+			}
+		}
+	}
+
+	/// Parses the optional label and the `;` after `break` or `continue` and
+	/// returns the index of the loop it refers to, reporting an error if
+	/// there is no such loop.
+	fn loop_target(&mut self, keyword: &str) -> Result<Option<usize>, RLoxError> {
+		let label = if self.match_token(TokenType::Identifier)? {
+			Some(self.prev()?.slice(self.scanner.source))
+		} else {
+			None
+		};
+
+		// Only an unlabeled `break` can target a switch.
+		let targets_switch = keyword == "break";
+		let loops = &self.state().loops;
+		let index = match label {
+			Some(label) => loops.iter().rposition(|current| current.label == Some(label)),
+			None => loops.iter().rposition(|current| targets_switch || !current.is_switch),
+		};
+
+		match (index, label) {
+			(Some(_), _) => {}
+			(None, Some(label)) => self.error(&format!("No enclosing loop labeled '{}'.", label)),
+			(None, None) if targets_switch => self.error("Can't use 'break' outside of a loop or switch."),
+			(None, None) => self.error(&format!("Can't use '{}' outside of a loop.", keyword)),
+		}
+
+		self.consume(TokenType::SemiColon, &format!("Expect ';' after '{}'.", keyword))?;
+		Ok(index)
+	}
+
+	fn begin_loop(&mut self, label: Option<&'src str>, continue_target: Option<usize>) {
+		let scope_depth = self.state().scope_depth;
+		self.state_mut().loops.push(LoopState {
+			label,
+			is_switch: false,
+			scope_depth,
+			continue_target,
+			continue_jumps: Vec::new(),
+			break_jumps: Vec::new(),
+		});
+	}
+
+	fn begin_switch(&mut self) {
+		self.begin_loop(None, None);
+		if let Some(switch) = self.state_mut().loops.last_mut() {
+			switch.is_switch = true;
+		}
+	}
+
+	fn end_loop(&mut self) {
+		if let Some(ended) = self.state_mut().loops.pop() {
+			for jump in ended.break_jumps {
+				self.patch_jump(jump);
+			}
+		}
+	}
+
+	fn block(&mut self) -> Result<(), RLoxError> {
+		while !self.check(TokenType::RightBrace)? && !self.check(TokenType::EOF)? {
+			self.declaration()?;
+		}
+
+		self.consume(TokenType::RightBrace, "Expect '}' after block.")
+	}
+
+	fn begin_scope(&mut self) {
+		self.state_mut().scope_depth += 1;
+	}
+
+	fn end_scope(&mut self) -> Result<(), RLoxError> {
+		self.state_mut().scope_depth -= 1;
+
+		let remaining = self.discard_locals(self.state().scope_depth)?;
+		self.state_mut().locals.truncate(remaining);
+		Ok(())
+	}
+
+	/// Emits the code to pop every local declared deeper than `scope_depth`,
+	/// closing the captured ones, without forgetting them at compile time.
+	/// Returns how many locals remain.
+	fn discard_locals(&mut self, scope_depth: usize) -> Result<usize, RLoxError> {
+		let mut remaining = self.state().locals.len();
+		while let Some(local) = remaining.checked_sub(1).map(|index| &self.state().locals[index]) {
+			if local.depth.is_some_and(|depth| depth <= scope_depth) {
+				break;
+			}
+
+			if local.is_captured {
+				self.emit_byte(OpCode::OpCloseUpvalue as u8)?;
+			} else {
+				self.emit_byte(OpCode::OpPop as u8)?;
+			}
+			remaining -= 1;
+		}
+
+		Ok(remaining)
+	}
+
 	fn expression_statement(&mut self) -> Result<(), RLoxError> {
 		self.expression()?;
 		self.consume(TokenType::SemiColon, "Expect ';' after expression.")?;
@@ -234,6 +1391,12 @@ impl<'src> Compiler<'src> {
 		Ok(self.curr()?.token_type == token_type)
 	}
 
+	/// The token after the current one, scanned from a copy of the scanner
+	/// so that it is not consumed.
+	fn peek_next(&self) -> Result<Token, RLoxError> {
+		Ok(self.scanner.clone().scan_token()?)
+	}
+
 	fn curr(&self) -> Result<Token, CompilerError> {
 		self
 			.parser
@@ -248,6 +1411,24 @@ impl<'src> Compiler<'src> {
 			.ok_or(CompilerError::new(0, "Current token is undefined"))
 	}
 
+	fn return_statement(&mut self) -> Result<(), RLoxError> {
+		if self.state().function_type == FunctionType::Script {
+			self.error("Can't return from top-level code.");
+		}
+
+		if self.match_token(TokenType::SemiColon)? {
+			self.emit_return()
+		} else {
+			if self.state().function_type == FunctionType::Initializer {
+				self.error("Can't return a value from an initializer.");
+			}
+
+			self.expression()?;
+			self.consume(TokenType::SemiColon, "Expect ';' after return value.")?;
+			self.emit_byte(OpCode::OpReturn as u8)
+		}
+	}
+
 	fn print_statement(&mut self) -> Result<(), RLoxError> {
 		self.expression()?;
 		self.consume(TokenType::SemiColon, "Expect ';' after value.")?;
@@ -286,20 +1467,30 @@ impl<'src> Compiler<'src> {
 		self.parse_precedence(Precedence::Assignment)
 	} 
 
-	fn end(&mut self) -> Result<(), RLoxError> {
+	/// Finishes the innermost function being compiled and hands it back
+	/// along with the variables it captures.
+	fn end(&mut self) -> Result<(Function, Vec<UpvalueSlot>), RLoxError> {
 		self.emit_return()?;
 
-		#[cfg(feature = "debug_print_code")]{
+		let state = self.states.pop()
+			.ok_or(CompilerError::new(self.prev()?.line, "No function to end"))?;
+
+		#[cfg(feature = "debug_print_code")]
+		if !self.parser.had_error {
 			use crate::debug::Disassemble;
-			Disassemble::chunk(&*self.current_chunk.borrow(), "main")?;
+			Disassemble::chunk(&state.function.chunk, &state.function.to_string(), self.heap)?;
 		}
-		Ok(())
+
+		Ok((state.function, state.upvalues))
 	}
 
 	fn binary(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
 		let operator_type = self.prev()?.token_type;
 		let rule = self.get_rule(operator_type)?;
-		let new_precedence = Precedence::from_u8(rule.precedence + 1)
+
+		// `**` is right-associative: its right operand may itself be a `**`.
+		let right_associative = operator_type == TokenType::StarStar;
+		let new_precedence = Precedence::from_u8(rule.precedence + !right_associative as u8)
 			.ok_or(CompilerError::new(self.prev()?.line, "Invalid Precedence"))?;
 		
 		self.parse_precedence(new_precedence)?;
@@ -309,6 +1500,14 @@ impl<'src> Compiler<'src> {
 			TokenType::Minus => self.emit_byte(OpCode::OpSubtract as u8),
 			TokenType::Star => self.emit_byte(OpCode::OpMultiply as u8),
 			TokenType::Slash => self.emit_byte(OpCode::OpDivide as u8),
+			TokenType::Percent => self.emit_byte(OpCode::OpModulo as u8),
+			TokenType::TildeSlash => self.emit_byte(OpCode::OpIntDivide as u8),
+			TokenType::StarStar => self.emit_byte(OpCode::OpPower as u8),
+			TokenType::Ampersand => self.emit_byte(OpCode::OpBitAnd as u8),
+			TokenType::Pipe => self.emit_byte(OpCode::OpBitOr as u8),
+			TokenType::Caret => self.emit_byte(OpCode::OpBitXor as u8),
+			TokenType::LessLess => self.emit_byte(OpCode::OpShiftLeft as u8),
+			TokenType::GreaterGreater => self.emit_byte(OpCode::OpShiftRight as u8),
 			TokenType::BangEqual => self.emit_bytes(OpCode::OpEqual as u8, OpCode::OpNot as u8),
 			TokenType::EqualEqual => self.emit_byte(OpCode::OpEqual as u8),
 			TokenType::Greater => self.emit_byte(OpCode::OpGreater as u8),
@@ -319,6 +1518,99 @@ impl<'src> Compiler<'src> {
 		}
 	}
 
+	fn call(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		let arg_count = self.argument_list()?;
+		self.emit_bytes(OpCode::OpCall as u8, arg_count)
+	}
+
+	fn argument_list(&mut self) -> Result<u8, RLoxError> {
+		let mut arg_count = 0usize;
+
+		if !self.check(TokenType::RightParen)? {
+			loop {
+				self.expression()?;
+				if arg_count == u8::MAX as usize {
+					self.error("Can't have more than 255 arguments.");
+				}
+				arg_count += 1;
+
+				if !self.match_token(TokenType::Comma)? {
+					break;
+				}
+			}
+		}
+
+		self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+		Ok(arg_count as u8)
+	}
+
+	fn dot(&mut self, can_assign: bool) -> Result<(), RLoxError> {
+		self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+		let name = self.identifier_constant(&self.prev()?);
+
+		if self.match_token(TokenType::LeftParen)? {
+			let arg_count = self.argument_list()?;
+			self.emit_indexed(OpCode::OpInvoke, OpCode::OpInvokeLong, name)?;
+			self.emit_byte(arg_count)
+		} else {
+			self.target(Target::Property { name }, can_assign)
+		}
+	}
+
+	/// A prefix `++` or `--`. The operand is compiled as usual; the target
+	/// it ends with performs the increment, see `take_increment`.
+	fn increment(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		let operator = self.prev()?.token_type;
+		let enclosing = self.increment.replace(PendingIncrement { operator, depth: self.depth + 1 });
+
+		self.parse_precedence(Precedence::Call)?;
+
+		if std::mem::replace(&mut self.increment, enclosing).is_some() {
+			self.error("Invalid increment target.");
+		}
+		Ok(())
+	}
+
+	fn conditional(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		let else_jump = self.emit_jump(OpCode::OpJumpIfFalse)?;
+
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.expression()?;
+		self.consume(TokenType::Colon, "Expect ':' after then branch of conditional expression.")?;
+		let end_jump = self.emit_jump(OpCode::OpJump)?;
+
+		// The else branch parses at the same level, so `a ? b : c ? d : e`
+		// groups to the right.
+		self.patch_jump(else_jump);
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.parse_precedence(Precedence::Conditional)?;
+
+		self.patch_jump(end_jump);
+		Ok(())
+	}
+
+	fn and(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		let end_jump = self.emit_jump(OpCode::OpJumpIfFalse)?;
+
+		self.emit_byte(OpCode::OpPop as u8)?;
+		self.parse_precedence(Precedence::And)?;
+
+		self.patch_jump(end_jump);
+		Ok(())
+	}
+
+	fn or(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		let else_jump = self.emit_jump(OpCode::OpJumpIfFalse)?;
+		let end_jump = self.emit_jump(OpCode::OpJump)?;
+
+		self.patch_jump(else_jump);
+		self.emit_byte(OpCode::OpPop as u8)?;
+
+		self.parse_precedence(Precedence::Or)?;
+		self.patch_jump(end_jump);
+		Ok(())
+	}
+
 	fn literal(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
 		match self.prev()?.token_type {
 			TokenType::True => self.emit_byte(OpCode::OpTrue as u8),
@@ -330,12 +1622,10 @@ impl<'src> Compiler<'src> {
 
 	fn string(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
 		let prev = self.prev()?;
-		self.emit_constant(Value::obj(Obj::String(self.copy_string(prev.start + 1, prev.length - 2))))
-	}
-
-	fn copy_string(&self, start: usize, length: usize) -> String {
-		let string = &self.scanner.source[start..start + length];
-		(*string).to_string()
+		let raw = &self.scanner.source[prev.start + 1..prev.start + prev.length - 1];
+		let chars = scanner::unescape(raw).map_err(|message| CompilerError::new(prev.line, message))?;
+		let string = self.heap.intern(&chars);
+		self.emit_constant(Value::obj(string))
 	}
 
 	fn get_rule(&mut self, token_type: TokenType) -> Result<&ParseRule, CompilerError> {
@@ -349,12 +1639,102 @@ impl<'src> Compiler<'src> {
 	}
 
 	fn number(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
+		let value = self.number_literal()?;
+	    self.emit_constant(value)
+	}
+
+	/// The value of the number literal just consumed. Literals that can't
+	/// be represented are reported and compile to zero.
+	fn number_literal(&mut self) -> Result<Value, RLoxError> {
 		let prev = self.prev()?;
-	
-		let value = Value::Number(prev.slice(self.scanner.source).parse()
-			.map_err(|e| CompilerError::new(prev.line, &format!("Unable to convert token to a number: {}", e).to_owned()))?);
+		let text = prev.slice(self.scanner.source);
 
-	    self.emit_constant(value)
+		if let Some(text) = text.strip_suffix('n') {
+			let (radix, digits) = Self::literal_digits(text);
+			let n = BigInt::parse(&digits, radix)
+				.ok_or(CompilerError::new(prev.line, "Unable to convert token to a big integer"))?;
+			return Ok(Value::obj(self.heap.alloc(Obj::BigInt(n))));
+		}
+
+		match Self::parse_number(text) {
+			Ok(NumberLiteral::Int(n)) => Ok(Value::int_in(n, self.heap)),
+			Ok(NumberLiteral::Float(n)) => Ok(Value::number(n)),
+			Err(message) => {
+				self.error(message);
+				Ok(Value::int(0))
+			}
+		}
+	}
+
+	/// Parses a number literal the scanner accepted. Literals with a
+	/// fraction or exponent are floats. Integer literals are integers if
+	/// they fit an `i64`, or else floats if exactly representable, see
+	/// `exact_f64`. No literal may overflow to infinity.
+	fn parse_number(text: &str) -> Result<NumberLiteral, &'static str> {
+		let (radix, digits) = Self::literal_digits(text);
+		if radix == 10 && digits.contains(['.', 'e', 'E']) {
+			let n: f64 = digits.parse().map_err(|_| "Invalid number literal.")?;
+			return Some(n).filter(|n| n.is_finite()).map(NumberLiteral::Float).ok_or("Number literal is too large.");
+		}
+
+		let limbs = Self::literal_limbs(&digits, radix);
+		let n = limbs.iter().rev().try_fold(0i64, |n, &limb| n.checked_mul(1 << 32)?.checked_add(limb as i64));
+		if let Some(n) = n {
+			return Ok(NumberLiteral::Int(n));
+		}
+
+		Self::exact_f64(&limbs)
+			.map(NumberLiteral::Float)
+			.ok_or("Integer literal can't be represented exactly as a number.")
+	}
+
+	/// Splits a number literal into its radix and its digits, without the
+	/// prefix and the `_` separators.
+	fn literal_digits(text: &str) -> (u32, String) {
+		let text = text.replace('_', "");
+		match text.get(..2) {
+			Some("0x" | "0X") => (16, text[2..].to_string()),
+			Some("0b" | "0B") => (2, text[2..].to_string()),
+			Some("0o" | "0O") => (8, text[2..].to_string()),
+			_ => (10, text),
+		}
+	}
+
+	/// The magnitude of an integer literal as little-endian 32-bit limbs,
+	/// without leading zero limbs, however many digits it has.
+	fn literal_limbs(digits: &str, radix: u32) -> Vec<u32> {
+		let mut limbs: Vec<u32> = Vec::new();
+		for digit in digits.chars().filter_map(|c| c.to_digit(radix)) {
+			let mut carry = digit as u64;
+			for limb in limbs.iter_mut() {
+				let n = *limb as u64 * radix as u64 + carry;
+				*limb = n as u32;
+				carry = n >> 32;
+			}
+			if carry > 0 {
+				limbs.push(carry as u32);
+			}
+		}
+		limbs
+	}
+
+	/// The integer as an `f64`, if exactly representable: an `f64` holds
+	/// integers of up to 53 significant bits, trailing zero bits aside,
+	/// below 2^1024.
+	fn exact_f64(limbs: &[u32]) -> Option<f64> {
+		let Some(low) = limbs.iter().position(|&limb| limb != 0) else {
+			return Some(0.0);
+		};
+		let top = limbs.len() - 1;
+		let bits = top as u32 * 32 + u32::BITS - limbs[top].leading_zeros();
+		let zeros = low as u32 * 32 + limbs[low].trailing_zeros();
+		if bits - zeros > f64::MANTISSA_DIGITS || bits > f64::MAX_EXP as u32 {
+			return None;
+		}
+
+		let mantissa = (zeros..bits).rev()
+			.fold(0u64, |mantissa, bit| mantissa << 1 | (limbs[bit as usize / 32] >> (bit % 32) & 1) as u64);
+		Some(mantissa as f64 * 2f64.powi(zeros as i32))
 	}
 
 	fn unary(&mut self, _can_assign: bool) -> Result<(), RLoxError> {
@@ -371,17 +1751,31 @@ impl<'src> Compiler<'src> {
 				self.emit_byte(OpCode::OpNegate as u8)
 			}
 
+			TokenType::Tilde => {
+				self.emit_byte(OpCode::OpBitNot as u8)
+			}
+
 			_ => unreachable!()
 		}
 	}
 
 	fn parse_precedence(&mut self, precedence: Precedence) -> Result<(), RLoxError> {
+		self.depth += 1;
+		let result = self.parse_operators(precedence);
+		self.depth -= 1;
+		result
+	}
+
+	fn parse_operators(&mut self, precedence: Precedence) -> Result<(), RLoxError> {
 		self.advance()?;
 
 		let mut prev = self.prev()?;
 		let prefix_rule = match self.get_rule(prev.token_type)?.prefix {
 			Some(prefix) => prefix,
-			None => return Ok(self.error("Expect expression.")),
+			None => {
+				self.error("Expect expression.");
+				return Ok(());
+			}
 		};
 
 		let pre = precedence as u8;
@@ -392,41 +1786,99 @@ impl<'src> Compiler<'src> {
 			self.advance()?;
 			prev = self.prev()?;
 
-			let infix_rule =  match self.get_rule(prev.token_type)?.infix {
-				Some(prefix) => prefix,
-				None => return Ok(self.error("Expect expression.")),
+			let infix_rule = match self.get_rule(prev.token_type)?.infix {
+				Some(infix) => infix,
+				None => {
+					self.error("Expect expression.");
+					return Ok(());
+				}
 			};
 
 			infix_rule(self, can_assign)?;
 		}
 
-		if can_assign && self.match_token(TokenType::Equal)? {
+		if can_assign && (self.match_token(TokenType::Equal)? || self.match_compound_assignment()?.is_some()) {
 			self.error("Invalid assignment target.");
 		}
 
 		Ok(())
 	}
 
-	fn make_constant(&mut self, value: Value) -> u8 {
-		let constant = self.current_chunk.borrow_mut().add_constant(Rc::new(value)) as u8;
-		if constant > u8::MAX {
+	/// Adds `value` to the constant pool. The index is emitted with
+	/// `emit_indexed`, so it may take up to three bytes.
+	fn make_constant(&mut self, value: Value) -> u32 {
+		let constant = self.current_chunk().add_constant(value);
+		if constant >= CONSTANTS_MAX {
 			self.error("Too many constants in one chunk.");
 			return 0
 		}
-		constant
+		constant as u32
 	}
 
 	fn emit_constant(&mut self, value: Value) -> Result<(), RLoxError> {
-		let pos = self.make_constant(value);
-		self.emit_bytes(OpCode::OpConstant as u8, pos)
+		let constant = self.make_constant(value);
+		self.emit_indexed(OpCode::OpConstant, OpCode::OpConstantLong, constant)
+	}
+
+	/// Emits `short` with a one-byte operand when `index` fits, and `long`
+	/// with a three-byte operand otherwise.
+	fn emit_indexed(&mut self, short: OpCode, long: OpCode, index: u32) -> Result<(), RLoxError> {
+		if index <= u8::MAX as u32 {
+			return self.emit_bytes(short as u8, index as u8);
+		}
+
+		self.emit_byte(long as u8)?;
+		self.emit_byte((index >> 16) as u8)?;
+		self.emit_bytes((index >> 8) as u8, index as u8)
 	}
 
 	fn emit_byte(&mut self, byte: u8) -> Result<(), RLoxError> {
-		self.current_chunk.borrow_mut().write(byte as u8, self.prev()?.line);
+		let line = self.prev()?.line;
+		self.current_chunk().write(byte, line);
 		Ok(())
 	}
 
+	fn emit_jump(&mut self, instruction: OpCode) -> Result<usize, RLoxError> {
+		self.emit_byte(instruction as u8)?;
+		self.emit_bytes(0xff, 0xff)?;
+		Ok(self.current_chunk().size() - 2)
+	}
+
+	fn patch_jump(&mut self, offset: usize) {
+		// -2 to adjust for the bytecode for the jump offset itself.
+		let jump = self.current_chunk().size() - offset - 2;
+		self.write_jump(offset, jump);
+	}
+
+	/// Writes the forward distance `jump` into the two bytes at `offset`.
+	fn write_jump(&mut self, offset: usize, jump: usize) {
+		if jump > u16::MAX as usize {
+			self.error("Too much code to jump over.");
+		}
+
+		let mut code = self.current_chunk().code.borrow_mut();
+		code[offset] = ((jump >> 8) & 0xff) as u8;
+		code[offset + 1] = (jump & 0xff) as u8;
+	}
+
+	fn emit_loop(&mut self, loop_start: usize) -> Result<(), RLoxError> {
+		self.emit_byte(OpCode::OpLoop as u8)?;
+
+		let offset = self.current_chunk().size() - loop_start + 2;
+		if offset > u16::MAX as usize {
+			self.error("Loop body too large.");
+		}
+
+		self.emit_bytes(((offset >> 8) & 0xff) as u8, (offset & 0xff) as u8)
+	}
+
 	fn emit_return(&mut self) -> Result<(), RLoxError> {
+		if self.state().function_type == FunctionType::Initializer {
+			self.emit_bytes(OpCode::OpGetLocal as u8, 0)?;
+		} else {
+			self.emit_byte(OpCode::OpNil as u8)?;
+		}
+
 		self.emit_byte(OpCode::OpReturn as u8)
 	}
 
@@ -435,6 +1887,17 @@ impl<'src> Compiler<'src> {
 		self.emit_byte(byte2)
 	}
 
+	/// Emits a variable access. Global slots past 255 switch to the long
+	/// form of the instruction; local and upvalue slots always fit in a byte.
+	fn emit_variable_op(&mut self, op: OpCode, slot: u32) -> Result<(), RLoxError> {
+		match op {
+			OpCode::OpDefineGlobal => self.emit_indexed(op, OpCode::OpDefineGlobalLong, slot),
+			OpCode::OpGetGlobal => self.emit_indexed(op, OpCode::OpGetGlobalLong, slot),
+			OpCode::OpSetGlobal => self.emit_indexed(op, OpCode::OpSetGlobalLong, slot),
+			_ => self.emit_bytes(op as u8, slot as u8),
+		}
+	}
+
 	fn error_at_current(&mut self, message: &str) {
 		self.error_at(self.parser.current, message)
 	}
@@ -460,6 +1923,5 @@ impl<'src> Compiler<'src> {
 
 		eprintln!(": {}", message);
 		self.parser.had_error = true;
-		return;
 	}
 }
\ No newline at end of file
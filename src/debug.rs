@@ -1,32 +1,30 @@
-use std::usize;
-
 use num_traits::FromPrimitive;
 
-use crate::{chunk::{Chunk, OpCode}, error::{RLoxError, RuntimeError}};
+use crate::{chunk::{Chunk, OpCode}, error::{RLoxError, RuntimeError}, memory::Heap};
 
 pub struct Disassemble {
 
 }
 
 impl Disassemble {
-	pub fn chunk(chunk: &Chunk, name: &str) -> Result<(), RLoxError>  {
+	pub fn chunk(chunk: &Chunk, name: &str, heap: &Heap) -> Result<(), RLoxError>  {
 		println!("== {} ==", name);
 
 		let mut offset = 0usize;
 		while offset < chunk.size() {
-			offset = Self::instruction(chunk, offset)?;
+			offset = Self::instruction(chunk, offset, heap)?;
 		}
 
 		Ok(())
 	}
 
-	pub fn instruction(chunk: &Chunk, offset: usize) -> Result<usize, RLoxError> {
+	pub fn instruction(chunk: &Chunk, offset: usize, heap: &Heap) -> Result<usize, RLoxError> {
 		print!("{:04} ", offset);
 
 		
-		let line = chunk.lines.get(offset).ok_or(RuntimeError::new(0, &format!("Failed to get line for {}", offset).to_owned()))?;
+		let line = chunk.line_at(offset).ok_or(RuntimeError::new(0, &format!("Failed to get line for {}", offset).to_owned()))?;
 		if offset > 0 {
-			let prev_line = chunk.lines.get(offset - 1).ok_or(RuntimeError::new(*line, &format!("Failed to get line for {}", offset).to_owned()))?;
+			let prev_line = chunk.line_at(offset - 1).ok_or(RuntimeError::new(line, &format!("Failed to get line for {}", offset).to_owned()))?;
 
 			if line == prev_line {
 				print!("   | ");
@@ -39,9 +37,9 @@ impl Disassemble {
 
 		let code = chunk.code.borrow();
 		let instruction = code.get(offset)
-				.ok_or(RuntimeError::new(*line, &format!("Failed to instruction on line {}.", offset).to_owned()))?;
+				.ok_or(RuntimeError::new(line, &format!("Failed to instruction on line {}.", offset).to_owned()))?;
 
-		return match OpCode::from_u8(*instruction) {
+		match OpCode::from_u8(*instruction) {
 			Some(OpCode::OpReturn) => Ok(Self::simple_instruction("OpReturn", offset)?),
 			Some(OpCode::OpNegate) => Ok(Self::simple_instruction("OpNegate", offset)?),
 			Some(OpCode::OpNot) => Ok(Self::simple_instruction("OpNot", offset)?),
@@ -49,33 +47,178 @@ impl Disassemble {
 			Some(OpCode::OpSubtract) => Ok(Self::simple_instruction("OpSubtract", offset)?),
 			Some(OpCode::OpDivide) => Ok(Self::simple_instruction("OpDivide", offset)?),
 			Some(OpCode::OpMultiply) => Ok(Self::simple_instruction("OpMultiply", offset)?),
-			Some(OpCode::OpConstant) => Ok(Self::constant_instruction("OpConstant", chunk, offset)?),
-			Some(OpCode::OpDefineGlobal) => Ok(Self::constant_instruction("OpDefineGlobal", chunk, offset)?),
-			Some(OpCode::OpGetGlobal) => Ok(Self::constant_instruction("OpGetGlobal", chunk, offset)?),
-			Some(OpCode::OpSetGlobal) => Ok(Self::constant_instruction("OpSetGlobal", chunk, offset)?),
+			Some(OpCode::OpModulo) => Ok(Self::simple_instruction("OpModulo", offset)?),
+			Some(OpCode::OpIntDivide) => Ok(Self::simple_instruction("OpIntDivide", offset)?),
+			Some(OpCode::OpPower) => Ok(Self::simple_instruction("OpPower", offset)?),
+			Some(OpCode::OpBitAnd) => Ok(Self::simple_instruction("OpBitAnd", offset)?),
+			Some(OpCode::OpBitOr) => Ok(Self::simple_instruction("OpBitOr", offset)?),
+			Some(OpCode::OpBitXor) => Ok(Self::simple_instruction("OpBitXor", offset)?),
+			Some(OpCode::OpBitNot) => Ok(Self::simple_instruction("OpBitNot", offset)?),
+			Some(OpCode::OpShiftLeft) => Ok(Self::simple_instruction("OpShiftLeft", offset)?),
+			Some(OpCode::OpShiftRight) => Ok(Self::simple_instruction("OpShiftRight", offset)?),
+			Some(OpCode::OpConstant) => Ok(Self::constant_instruction("OpConstant", chunk, offset, heap)?),
+			Some(OpCode::OpConstantLong) => Ok(Self::constant_long_instruction("OpConstantLong", chunk, offset, heap)?),
+			Some(OpCode::OpDefineGlobal) => Ok(Self::byte_instruction("OpDefineGlobal", chunk, offset)?),
+			Some(OpCode::OpDefineGlobalLong) => Ok(Self::long_instruction("OpDefineGlobalLong", chunk, offset)?),
+			Some(OpCode::OpGetGlobal) => Ok(Self::byte_instruction("OpGetGlobal", chunk, offset)?),
+			Some(OpCode::OpGetGlobalLong) => Ok(Self::long_instruction("OpGetGlobalLong", chunk, offset)?),
+			Some(OpCode::OpSetGlobal) => Ok(Self::byte_instruction("OpSetGlobal", chunk, offset)?),
+			Some(OpCode::OpSetGlobalLong) => Ok(Self::long_instruction("OpSetGlobalLong", chunk, offset)?),
 			Some(OpCode::OpNil) => Ok(Self::simple_instruction("OpNil", offset)?),
 			Some(OpCode::OpTrue) => Ok(Self::simple_instruction("OpTrue", offset)?),
 			Some(OpCode::OpFalse) => Ok(Self::simple_instruction("OpFalse", offset)?),
 			Some(OpCode::OpEqual) => Ok(Self::simple_instruction("OpEqual", offset)?),
 			Some(OpCode::OpLess) => Ok(Self::simple_instruction("OpLess", offset)?),
 			Some(OpCode::OpPrint) => Ok(Self::simple_instruction("OpPrint", offset)?),
+			Some(OpCode::OpJump) => Ok(Self::jump_instruction("OpJump", 1, chunk, offset)?),
+			Some(OpCode::OpJumpIfFalse) => Ok(Self::jump_instruction("OpJumpIfFalse", 1, chunk, offset)?),
+			Some(OpCode::OpJumpTable) => Ok(Self::jump_table_instruction("OpJumpTable", chunk, offset)?),
+			Some(OpCode::OpLoop) => Ok(Self::jump_instruction("OpLoop", -1, chunk, offset)?),
+			Some(OpCode::OpCall) => Ok(Self::byte_instruction("OpCall", chunk, offset)?),
+			Some(OpCode::OpGetUpvalue) => Ok(Self::byte_instruction("OpGetUpvalue", chunk, offset)?),
+			Some(OpCode::OpSetUpvalue) => Ok(Self::byte_instruction("OpSetUpvalue", chunk, offset)?),
+			Some(OpCode::OpClosure) => Ok(Self::closure_instruction("OpClosure", chunk, offset, heap, false)?),
+			Some(OpCode::OpClosureLong) => Ok(Self::closure_instruction("OpClosureLong", chunk, offset, heap, true)?),
+			Some(OpCode::OpCloseUpvalue) => Ok(Self::simple_instruction("OpCloseUpvalue", offset)?),
+			Some(OpCode::OpGetProperty) => Ok(Self::constant_instruction("OpGetProperty", chunk, offset, heap)?),
+			Some(OpCode::OpGetPropertyLong) => Ok(Self::constant_long_instruction("OpGetPropertyLong", chunk, offset, heap)?),
+			Some(OpCode::OpSetProperty) => Ok(Self::constant_instruction("OpSetProperty", chunk, offset, heap)?),
+			Some(OpCode::OpSetPropertyLong) => Ok(Self::constant_long_instruction("OpSetPropertyLong", chunk, offset, heap)?),
+			Some(OpCode::OpClass) => Ok(Self::constant_instruction("OpClass", chunk, offset, heap)?),
+			Some(OpCode::OpClassLong) => Ok(Self::constant_long_instruction("OpClassLong", chunk, offset, heap)?),
+			Some(OpCode::OpInherit) => Ok(Self::simple_instruction("OpInherit", offset)?),
+			Some(OpCode::OpGetSuper) => Ok(Self::constant_instruction("OpGetSuper", chunk, offset, heap)?),
+			Some(OpCode::OpGetSuperLong) => Ok(Self::constant_long_instruction("OpGetSuperLong", chunk, offset, heap)?),
+			Some(OpCode::OpSuperInvoke) => Ok(Self::invoke_instruction("OpSuperInvoke", chunk, offset, heap, false)?),
+			Some(OpCode::OpSuperInvokeLong) => Ok(Self::invoke_instruction("OpSuperInvokeLong", chunk, offset, heap, true)?),
+			Some(OpCode::OpMethod) => Ok(Self::constant_instruction("OpMethod", chunk, offset, heap)?),
+			Some(OpCode::OpMethodLong) => Ok(Self::constant_long_instruction("OpMethodLong", chunk, offset, heap)?),
+			Some(OpCode::OpInvoke) => Ok(Self::invoke_instruction("OpInvoke", chunk, offset, heap, false)?),
+			Some(OpCode::OpInvokeLong) => Ok(Self::invoke_instruction("OpInvokeLong", chunk, offset, heap, true)?),
 			Some(OpCode::OpGreater) => Ok(Self::simple_instruction("OpGreater", offset)?),
 			Some(OpCode::OpPop) => Ok(Self::simple_instruction("OpPop", offset)?),
+			Some(OpCode::OpDup) => Ok(Self::simple_instruction("OpDup", offset)?),
+			Some(OpCode::OpSwap) => Ok(Self::simple_instruction("OpSwap", offset)?),
+			Some(OpCode::OpOver) => Ok(Self::simple_instruction("OpOver", offset)?),
+			Some(OpCode::OpGetLocal) => Ok(Self::byte_instruction("OpGetLocal", chunk, offset)?),
+			Some(OpCode::OpSetLocal) => Ok(Self::byte_instruction("OpSetLocal", chunk, offset)?),
 			_ => {
 				println!("Unknown opcode {}", instruction);
-				return Ok(offset + 1);
+				Ok(offset + 1)
 			}
 		}
 	}
 
-	fn constant_instruction(name: &str, chunk: &Chunk, offset: usize) -> Result<usize, RuntimeError> {
+	fn constant_instruction(name: &str, chunk: &Chunk, offset: usize, heap: &Heap) -> Result<usize, RuntimeError> {
 		let code = chunk.code.borrow();
 		let constant = code.get(offset + 1).ok_or(RuntimeError::new(0, "message"))?;
 		let value = chunk.constants.get(*constant as usize).ok_or(RuntimeError::new(0, "message"))?;
-		println!("{:<16} {:04} '{}'", name, constant, value);
+		println!("{:<16} {:04} '{}'", name, constant, value.display(heap));
+		Ok(offset + 2)
+	}
+
+	fn closure_instruction(name: &str, chunk: &Chunk, offset: usize, heap: &Heap, long: bool) -> Result<usize, RuntimeError> {
+		let (constant, mut offset) = Self::index_operand(chunk, offset, long)?;
+		let code = chunk.code.borrow();
+		let value = chunk.constants.get(constant as usize).ok_or(RuntimeError::new(0, "Missing constant for closure instruction"))?;
+		println!("{:<16} {:04} {}", name, constant, value.display(heap));
+
+		let upvalue_count = match value.as_obj() {
+			Some(obj) => heap.get(obj).as_function().map_or(0, |function| function.upvalue_count),
+			None => 0,
+		};
+
+		for _ in 0..upvalue_count {
+			let is_local = code.get(offset).ok_or(RuntimeError::new(0, "Missing upvalue for closure instruction"))?;
+			let index = code.get(offset + 1).ok_or(RuntimeError::new(0, "Missing upvalue for closure instruction"))?;
+			println!("{:04}    |                     {} {}", offset, if *is_local == 1 { "local" } else { "upvalue" }, index);
+			offset += 2;
+		}
+
+		Ok(offset)
+	}
+
+	fn invoke_instruction(name: &str, chunk: &Chunk, offset: usize, heap: &Heap, long: bool) -> Result<usize, RuntimeError> {
+		let (constant, offset) = Self::index_operand(chunk, offset, long)?;
+		let code = chunk.code.borrow();
+		let arg_count = code.get(offset).ok_or(RuntimeError::new(0, "Missing operand for invoke instruction"))?;
+		let value = chunk.constants.get(constant as usize).ok_or(RuntimeError::new(0, "Missing constant for invoke instruction"))?;
+		println!("{:<16} ({} args) {:04} '{}'", name, arg_count, constant, value.display(heap));
+		Ok(offset + 1)
+	}
+
+	fn byte_instruction(name: &str, chunk: &Chunk, offset: usize) -> Result<usize, RuntimeError> {
+		let code = chunk.code.borrow();
+		let slot = code.get(offset + 1).ok_or(RuntimeError::new(0, "Missing operand for byte instruction"))?;
+		println!("{:<16} {:04}", name, slot);
 		Ok(offset + 2)
 	}
 
+	fn constant_long_instruction(name: &str, chunk: &Chunk, offset: usize, heap: &Heap) -> Result<usize, RuntimeError> {
+		let constant = Self::long_operand(chunk, offset)?;
+		let value = chunk.constants.get(constant as usize).ok_or(RuntimeError::new(0, "Missing constant for long constant instruction"))?;
+		println!("{:<16} {:04} '{}'", name, constant, value.display(heap));
+		Ok(offset + 4)
+	}
+
+	fn long_instruction(name: &str, chunk: &Chunk, offset: usize) -> Result<usize, RuntimeError> {
+		println!("{:<16} {:04}", name, Self::long_operand(chunk, offset)?);
+		Ok(offset + 4)
+	}
+
+	/// The constant index following the instruction at `offset`, one or
+	/// three bytes wide, and the offset just past it.
+	fn index_operand(chunk: &Chunk, offset: usize, long: bool) -> Result<(u32, usize), RuntimeError> {
+		if long {
+			return Ok((Self::long_operand(chunk, offset)?, offset + 4));
+		}
+
+		let code = chunk.code.borrow();
+		let index = code.get(offset + 1).ok_or(RuntimeError::new(0, "Missing operand for instruction"))?;
+		Ok((*index as u32, offset + 2))
+	}
+
+	/// The three-byte operand following the instruction at `offset`.
+	fn long_operand(chunk: &Chunk, offset: usize) -> Result<u32, RuntimeError> {
+		let code = chunk.code.borrow();
+		let bytes = code.get(offset + 1..offset + 4).ok_or(RuntimeError::new(0, "Missing operand for long instruction"))?;
+		Ok((bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32)
+	}
+
+	fn jump_instruction(name: &str, sign: isize, chunk: &Chunk, offset: usize) -> Result<usize, RuntimeError> {
+		let code = chunk.code.borrow();
+		let hi = code.get(offset + 1).ok_or(RuntimeError::new(0, "Missing operand for jump instruction"))?;
+		let lo = code.get(offset + 2).ok_or(RuntimeError::new(0, "Missing operand for jump instruction"))?;
+		let jump = ((*hi as u16) << 8 | *lo as u16) as isize;
+		println!("{:<16} {:04} -> {}", name, offset, offset as isize + 3 + sign * jump);
+		Ok(offset + 3)
+	}
+
+	fn jump_table_instruction(name: &str, chunk: &Chunk, offset: usize) -> Result<usize, RuntimeError> {
+		let code = chunk.code.borrow();
+		let short = |at: usize| -> Result<u16, RuntimeError> {
+			let bytes = code.get(at..at + 2).ok_or(RuntimeError::new(0, "Missing operand for jump table instruction"))?;
+			Ok((bytes[0] as u16) << 8 | bytes[1] as u16)
+		};
+
+		let min = short(offset + 1)? as i16;
+		let len = short(offset + 3)? as usize;
+		let table_start = offset + 5;
+		let table_end = table_start + (len + 1) * 2;
+		println!("{:<16} {:04} [{}, {})", name, offset, min, min as isize + len as isize);
+
+		for index in 0..=len {
+			let target = table_end + short(table_start + index * 2)? as usize;
+			if index == len {
+				println!("{:04}    |                     default -> {}", table_start + index * 2, target);
+			} else {
+				println!("{:04}    |                     {} -> {}", table_start + index * 2, min as isize + index as isize, target);
+			}
+		}
+
+		Ok(table_end)
+	}
+
 	fn simple_instruction(name: &str, offset: usize) -> Result<usize, RuntimeError> {
 		println!("{}", name);
 		Ok(offset + 1)
@@ -1,4 +1,5 @@
 pub mod value;
+pub mod bigint;
 pub mod chunk;
 pub mod debug;
 pub mod error;
@@ -6,4 +7,6 @@ pub mod vm;
 pub mod compiler;
 pub mod scanner;
 pub mod token;
-pub mod parser;
\ No newline at end of file
+pub mod parser;
+pub mod natives;
+pub mod memory;
\ No newline at end of file
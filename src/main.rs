@@ -1,12 +1,12 @@
 use std::io::{self, Write};
 use std::{env, fs::File, io::Read, process::exit};
 
-use rlox::error::RLoxError;
 use rlox::vm::VM;
 
 pub fn repl() -> Result<(), Box<dyn std::error::Error>> {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
+    let mut vm = VM::new();
     
     loop {
         print!("> ");
@@ -24,7 +24,7 @@ pub fn repl() -> Result<(), Box<dyn std::error::Error>> {
             break;
         }
 
-        if let Err(e) = interpret(line) {
+        if let Err(e) = vm.interpret(line) {
             eprintln!("Error {}", e);
         }
 
@@ -38,16 +38,12 @@ pub fn run_file(path: &str) -> Result<(), Box<dyn std::error::Error>> {
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
     
-	match interpret(&contents) {
-		Err(err) => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, format!("{}", err)))),
+	match VM::new().interpret(&contents) {
+		Err(err) => Err(Box::new(std::io::Error::other(format!("{}", err)))),
 		_ => Ok(())
 	}
 }
 
-fn interpret(source: &str) -> Result<(), RLoxError> {
-    VM::interpret(source)
-}
-
 fn main() {
     let args: Vec<String> = env::args().collect();
 
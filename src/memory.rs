@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::mem;
+use std::rc::Rc;
+
+use crate::value::{Obj, Upvalue, Value};
+
+const HEAP_GROW_FACTOR: usize = 2;
+const INITIAL_NEXT_GC: usize = 1024 * 1024;
+
+/// A handle to an object owned by a `Heap`. Handles are only meaningful for
+/// the heap that created them and must stay reachable from a root to remain
+/// valid across a collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GcRef(usize);
+
+#[cfg(feature = "nan_boxing")]
+impl GcRef {
+	/// The heap slot behind the handle, for packing it into a NaN-boxed
+	/// `Value`.
+	pub(crate) fn to_index(self) -> usize {
+		self.0
+	}
+
+	pub(crate) fn from_index(index: usize) -> Self {
+		GcRef(index)
+	}
+}
+
+struct HeapEntry {
+	obj: Obj,
+	size: usize,
+	marked: bool,
+}
+
+/// Owns every Lox object and reclaims them with a tracing mark-and-sweep
+/// collector. The heap does not know its roots: the owner marks them with
+/// `mark_value`/`mark_object` and then calls `collect`.
+///
+/// Strings are interned through `intern`. The intern table does not keep
+/// its strings alive; entries are dropped when their string is swept.
+pub struct Heap {
+	entries: Vec<Option<HeapEntry>>,
+	free_slots: Vec<usize>,
+	strings: HashMap<Rc<str>, GcRef>,
+	gray_stack: Vec<GcRef>,
+	bytes_allocated: usize,
+	next_gc: usize,
+	grow_factor: usize,
+}
+
+impl Default for Heap {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Heap {
+	pub fn new() -> Self {
+		Self {
+			entries: Vec::new(),
+			free_slots: Vec::new(),
+			strings: HashMap::new(),
+			gray_stack: Vec::new(),
+			bytes_allocated: 0,
+			next_gc: INITIAL_NEXT_GC,
+			grow_factor: HEAP_GROW_FACTOR,
+		}
+	}
+
+	/// Moves `obj` onto the heap. Allocation never collects by itself; the
+	/// owner checks `should_collect` at points where all its roots are known.
+	pub fn alloc(&mut self, obj: Obj) -> GcRef {
+		let size = Self::size_of(&obj);
+		self.bytes_allocated += size;
+
+		let entry = HeapEntry { obj, size, marked: false };
+		match self.free_slots.pop() {
+			Some(slot) => {
+				self.entries[slot] = Some(entry);
+				GcRef(slot)
+			}
+			None => {
+				self.entries.push(Some(entry));
+				GcRef(self.entries.len() - 1)
+			}
+		}
+	}
+
+	/// Returns the string object holding `chars`, allocating it only if no
+	/// such string exists yet. Like `alloc`, this never collects.
+	pub fn intern(&mut self, chars: &str) -> GcRef {
+		if let Some(reference) = self.strings.get(chars) {
+			return *reference;
+		}
+
+		let chars: Rc<str> = Rc::from(chars);
+		let reference = self.alloc(Obj::String(Rc::clone(&chars)));
+		self.strings.insert(chars, reference);
+		reference
+	}
+
+	pub fn get(&self, reference: GcRef) -> &Obj {
+		match &self.entries[reference.0] {
+			Some(entry) => &entry.obj,
+			None => panic!("Use of freed object {:?}", reference),
+		}
+	}
+
+	pub fn get_mut(&mut self, reference: GcRef) -> &mut Obj {
+		match &mut self.entries[reference.0] {
+			Some(entry) => &mut entry.obj,
+			None => panic!("Use of freed object {:?}", reference),
+		}
+	}
+
+	pub fn bytes_allocated(&self) -> usize {
+		self.bytes_allocated
+	}
+
+	/// Sets how much the heap may grow, relative to the live size after a
+	/// collection, before the next collection is due. Factors below one are
+	/// treated as one.
+	pub fn set_grow_factor(&mut self, factor: usize) {
+		self.grow_factor = factor.max(1);
+	}
+
+	pub fn should_collect(&self) -> bool {
+		cfg!(feature = "gc_stress") || self.bytes_allocated > self.next_gc
+	}
+
+	pub fn mark_value(&mut self, value: Value) {
+		if let Some(reference) = value.as_obj() {
+			self.mark_object(reference);
+		}
+	}
+
+	pub fn mark_object(&mut self, reference: GcRef) {
+		if let Some(entry) = &mut self.entries[reference.0] {
+			if entry.marked {
+				return;
+			}
+
+			entry.marked = true;
+			self.gray_stack.push(reference);
+		}
+	}
+
+	/// Traces everything reachable from the marked roots and frees the rest.
+	pub fn collect(&mut self) {
+		self.trace_references();
+		self.sweep();
+		self.next_gc = (self.bytes_allocated * self.grow_factor).max(INITIAL_NEXT_GC);
+	}
+
+	fn trace_references(&mut self) {
+		while let Some(reference) = self.gray_stack.pop() {
+			self.blacken_object(reference);
+		}
+	}
+
+	fn blacken_object(&mut self, reference: GcRef) {
+		let mut children = Vec::new();
+		match self.get(reference) {
+			Obj::String(_) | Obj::Int(_) | Obj::BigInt(_) | Obj::Native(_) => {}
+			Obj::Class(class) => {
+				children.push(Value::obj(class.name));
+				for (name, method) in &class.methods {
+					children.push(Value::obj(*name));
+					children.push(Value::obj(*method));
+				}
+			}
+			Obj::Function(function) => children.extend(function.chunk.constants.iter().copied()),
+			Obj::Closure(closure) => {
+				children.push(Value::obj(closure.function));
+				children.extend(closure.upvalues.iter().copied().map(Value::obj));
+			}
+			Obj::Upvalue(Upvalue::Closed(value)) => children.push(*value),
+			Obj::Upvalue(Upvalue::Open(_)) => {}
+			Obj::Instance(instance) => {
+				children.push(Value::obj(instance.class));
+				for (name, value) in &instance.fields {
+					children.push(Value::obj(*name));
+					children.push(*value);
+				}
+			}
+			Obj::BoundMethod(bound) => {
+				children.push(bound.receiver);
+				children.push(Value::obj(bound.method));
+			}
+		}
+
+		for child in children {
+			self.mark_value(child);
+		}
+	}
+
+	fn sweep(&mut self) {
+		for (slot, entry) in self.entries.iter_mut().enumerate() {
+			match entry {
+				Some(e) if e.marked => e.marked = false,
+				Some(e) => {
+					if let Obj::String(chars) = &e.obj {
+						self.strings.remove(chars);
+					}
+
+					self.bytes_allocated -= e.size;
+					*entry = None;
+					self.free_slots.push(slot);
+				}
+				None => {}
+			}
+		}
+	}
+
+	/// A rough count of the bytes `obj` keeps alive, used to pace collection.
+	fn size_of(obj: &Obj) -> usize {
+		let payload = match obj {
+			Obj::String(s) => s.len(),
+			Obj::BigInt(n) => n.heap_size(),
+			Obj::Function(function) => {
+				function.chunk.size()
+					+ function.chunk.constants.capacity() * mem::size_of::<Value>()
+			}
+			Obj::Closure(closure) => closure.upvalues.capacity() * mem::size_of::<GcRef>(),
+			Obj::Class(class) => class.methods.capacity() * mem::size_of::<(GcRef, GcRef)>(),
+			Obj::Instance(instance) => instance.fields.capacity() * mem::size_of::<(GcRef, Value)>(),
+			Obj::Int(_) | Obj::Native(_) | Obj::Upvalue(_) | Obj::BoundMethod(_) => 0,
+		};
+
+		mem::size_of::<HeapEntry>() + payload
+	}
+}
@@ -0,0 +1,53 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::RuntimeError;
+use crate::memory::Heap;
+use crate::value::Value;
+
+/// `clock()` - seconds since the Unix epoch, for timing scripts.
+pub fn clock(_heap: &mut Heap, _args: &[Value]) -> Result<Value, RuntimeError> {
+	let elapsed = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_err(|e| RuntimeError::new(0, &format!("System clock error: {}", e)))?;
+	Ok(Value::number(elapsed.as_secs_f64()))
+}
+
+/// `type(value)` - the name of the value's type as a string.
+pub fn type_of(heap: &mut Heap, args: &[Value]) -> Result<Value, RuntimeError> {
+	let name = args[0].type_name(heap);
+	Ok(Value::obj(heap.intern(name)))
+}
+
+/// `str(value)` - the value formatted the same way `print` would.
+pub fn str(heap: &mut Heap, args: &[Value]) -> Result<Value, RuntimeError> {
+	let text = args[0].display(heap).to_string();
+	Ok(Value::obj(heap.intern(&text)))
+}
+
+/// `num(value)` - numbers pass through, strings are parsed. Strings holding
+/// an integer that fits give an integer, others a float.
+pub fn num(heap: &mut Heap, args: &[Value]) -> Result<Value, RuntimeError> {
+	if args[0].as_float().is_some() {
+		return Ok(args[0]);
+	}
+
+	match args[0].as_obj() {
+		Some(o) => {
+			let obj = heap.get(o);
+			if obj.as_int().is_some() || obj.as_bigint().is_some() {
+				return Ok(args[0]);
+			}
+
+			let text = obj.as_string()
+				.ok_or(RuntimeError::new(0, &format!("Cannot convert {} to a number.", obj.type_name())))?
+				.trim()
+				.to_string();
+			if let Ok(n) = text.parse() {
+				return Ok(Value::int_in(n, heap));
+			}
+			text.parse().map(Value::number)
+				.map_err(|_| RuntimeError::new(0, &format!("Cannot convert '{}' to a number.", text)))
+		}
+		None => Err(RuntimeError::new(0, &format!("Cannot convert {} to a number.", args[0].type_name(heap)))),
+	}
+}
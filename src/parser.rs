@@ -7,6 +7,12 @@ pub struct Parser {
 	pub had_error: bool,
 }
 
+impl Default for Parser {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl Parser {
 	pub fn new() -> Self {
 		Self {
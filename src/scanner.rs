@@ -1,6 +1,8 @@
+use std::str::Chars;
+
 use crate::{error::TokenError, token::{Token, TokenType}};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Scanner<'src> {
     pub source: &'src str,
     start: usize,
@@ -36,49 +38,109 @@ impl<'src> Scanner<'src> {
 			return self.identifier();
 		}
 
-		return match c {
+		match c {
 			'"' => self.string(),
-			'(' => return Ok(self.make_token(TokenType::LeftParen)),
-			')' => return Ok(self.make_token(TokenType::RightParen)),
-			'{' => return Ok(self.make_token(TokenType::LeftBrace)),
-			'}' => return Ok(self.make_token(TokenType::RightBrace)),
-			';' => return Ok(self.make_token(TokenType::SemiColon)),
-			',' => return Ok(self.make_token(TokenType::Comma)),
-			'.' => return Ok(self.make_token(TokenType::Dot)),
-			'-' => return Ok(self.make_token(TokenType::Minus)),
-			'+' => return Ok(self.make_token(TokenType::Plus)),
-			'/' => return Ok(self.make_token(TokenType::Slash)),
-			'*' => return Ok(self.make_token(TokenType::Star)),
+			'(' => Ok(self.make_token(TokenType::LeftParen)),
+			')' => Ok(self.make_token(TokenType::RightParen)),
+			'{' => Ok(self.make_token(TokenType::LeftBrace)),
+			'}' => Ok(self.make_token(TokenType::RightBrace)),
+			';' => Ok(self.make_token(TokenType::SemiColon)),
+			',' => Ok(self.make_token(TokenType::Comma)),
+			'.' => Ok(self.make_token(TokenType::Dot)),
+			'&' => Ok(self.make_token(TokenType::Ampersand)),
+			'|' => Ok(self.make_token(TokenType::Pipe)),
+			'^' => Ok(self.make_token(TokenType::Caret)),
+
+			'-' => {
+				if self.match_str('=') {
+					Ok(self.make_token(TokenType::MinusEqual))
+				} else if self.match_str('-') {
+					Ok(self.make_token(TokenType::MinusMinus))
+				} else {
+					Ok(self.make_token(TokenType::Minus))
+				}
+			}
+
+			'+' => {
+				if self.match_str('=') {
+					Ok(self.make_token(TokenType::PlusEqual))
+				} else if self.match_str('+') {
+					Ok(self.make_token(TokenType::PlusPlus))
+				} else {
+					Ok(self.make_token(TokenType::Plus))
+				}
+			}
+
+			'/' => {
+				if self.match_str('=') {
+					Ok(self.make_token(TokenType::SlashEqual))
+				} else {
+					Ok(self.make_token(TokenType::Slash))
+				}
+			}
+
+			'%' => {
+				if self.match_str('=') {
+					Ok(self.make_token(TokenType::PercentEqual))
+				} else {
+					Ok(self.make_token(TokenType::Percent))
+				}
+			}
+
+			'*' => {
+				if self.match_str('*') {
+					Ok(self.make_token(TokenType::StarStar))
+				} else if self.match_str('=') {
+					Ok(self.make_token(TokenType::StarEqual))
+				} else {
+					Ok(self.make_token(TokenType::Star))
+				}
+			}
+
+			'~' => {
+				if self.match_str('/') {
+					Ok(self.make_token(TokenType::TildeSlash))
+				} else {
+					Ok(self.make_token(TokenType::Tilde))
+				}
+			}
+
+			':' => Ok(self.make_token(TokenType::Colon)),
+			'?' => Ok(self.make_token(TokenType::Question)),
 
 			'!' => {
 				if self.match_str('=') {
-					return Ok(self.make_token(TokenType::BangEqual));
+					Ok(self.make_token(TokenType::BangEqual))
 				} else {
-					return Ok(self.make_token(TokenType::Bang));
+					Ok(self.make_token(TokenType::Bang))
 				}
 			}
 
 			'=' => {
 				if self.match_str('=') {
-					return Ok(self.make_token(TokenType::EqualEqual));
+					Ok(self.make_token(TokenType::EqualEqual))
 				} else {
-					return Ok(self.make_token(TokenType::Equal));
+					Ok(self.make_token(TokenType::Equal))
 				}
 			}
 
 			'<' => {
 				if self.match_str('=') {
-					return Ok(self.make_token(TokenType::LessEqual));
+					Ok(self.make_token(TokenType::LessEqual))
+				} else if self.match_str('<') {
+					Ok(self.make_token(TokenType::LessLess))
 				} else {
-					return Ok(self.make_token(TokenType::Less));
+					Ok(self.make_token(TokenType::Less))
 				}
 			}
 
 			'>' => {
 				if self.match_str('=') {
-					return Ok(self.make_token(TokenType::GreaterEqual));
+					Ok(self.make_token(TokenType::GreaterEqual))
+				} else if self.match_str('>') {
+					Ok(self.make_token(TokenType::GreaterGreater))
 				} else {
-					return Ok(self.make_token(TokenType::Greater));
+					Ok(self.make_token(TokenType::Greater))
 				}
 			}
 
@@ -88,11 +150,17 @@ impl<'src> Scanner<'src> {
 
 	fn string(&mut self) -> Result<Token, TokenError> {
 		while self.peek()? != Some('"') && !self.is_at_end() {
-			if self.peek()? == Some('\n') {
-				self.line += 1;
+			match self.advance() {
+				'\n' => self.line += 1,
+				'\\' => {
+					// Only checked here; the compiler decodes the literal
+					// with `unescape`.
+					let mut chars = self.source[self.current..].chars();
+					escape(&mut chars).map_err(|message| TokenError::new(self.line, message))?;
+					self.current = self.source.len() - chars.as_str().len();
+				}
+				_ => {}
 			}
-
-			self.advance();
 		}
 
 		if self.is_at_end() {
@@ -103,22 +171,62 @@ impl<'src> Scanner<'src> {
 		Ok(self.make_token(TokenType::String))
 	}
 
+	/// Scans a decimal number with an optional fraction and exponent, or an
+	/// integer with a `0x`, `0b` or `0o` prefix. Digits may be separated by
+	/// `_`; the compiler strips the separators when it parses the literal.
+	/// Integers with an `n` suffix are big integers.
 	fn number(&mut self) -> Result<Token, TokenError> {
-		while self.is_digit(self.peek()?.unwrap_or('\0')) {
+		let radix = match (self.char_from_start(0)?, self.peek()?) {
+			(Some('0'), Some('x' | 'X')) => 16,
+			(Some('0'), Some('b' | 'B')) => 2,
+			(Some('0'), Some('o' | 'O')) => 8,
+			_ => 10,
+		};
+
+		if radix != 10 {
 			self.advance();
+			if !self.peek()?.is_some_and(|c| c.is_digit(radix)) {
+				return Err(TokenError::new(self.line, "Expect digits after number prefix."));
+			}
 		}
+		self.digits(radix)?;
 
-		if self.peek()? == Some('.') && self.is_digit(self.peek_next()?.unwrap_or('\0')) {
+		if self.peek()? == Some('n') {
 			self.advance();
+		} else if radix == 10 {
+			if self.peek()? == Some('.') && self.is_digit(self.peek_next()?.unwrap_or('\0')) {
+				self.advance();
+				self.digits(10)?;
+			}
 
-			while self.is_digit(self.peek()?.unwrap_or('\0')) {
+			if matches!(self.peek()?, Some('e' | 'E')) {
 				self.advance();
+				if matches!(self.peek()?, Some('+' | '-')) {
+					self.advance();
+				}
+
+				if !self.is_digit(self.peek()?.unwrap_or('\0')) {
+					return Err(TokenError::new(self.line, "Expect digits in exponent."));
+				}
+				self.digits(10)?;
 			}
 		}
 
+		if self.peek()?.is_some_and(|c| self.is_alpha_numeric(c)) {
+			return Err(TokenError::new(self.line, "Invalid digit in number literal."));
+		}
+
 		Ok(self.make_token(TokenType::Number))
 	}
 
+	fn digits(&mut self, radix: u32) -> Result<(), TokenError> {
+		while self.peek()?.is_some_and(|c| c.is_digit(radix) || c == '_') {
+			self.advance();
+		}
+
+		Ok(())
+	}
+
 	fn identifier(&mut self) -> Result<Token, TokenError> {
 		while self.is_alpha_numeric(self.peek()?.unwrap_or('\0')) {
 			self.advance();
@@ -128,32 +236,56 @@ impl<'src> Scanner<'src> {
 	}
 
 	fn identifier_type(&self) -> Result<TokenType, TokenError> {
-		match self.from_start(0)? {
+		match self.char_from_start(0)? {
 			Some('a') => Ok(self.check_keyword(1, "nd", TokenType::And)),
-			Some('c') => Ok(self.check_keyword(1, "lass", TokenType::Class)),
+			Some('b') => Ok(self.check_keyword(1, "reak", TokenType::Break)),
 			Some('e') => Ok(self.check_keyword(1, "lse", TokenType::Else)),
 			Some('i') => Ok(self.check_keyword(1, "f", TokenType::If)),
 			Some('n') => Ok(self.check_keyword(1, "il", TokenType::Nil)),
 			Some('o') => Ok(self.check_keyword(1, "r", TokenType::Or)),
 			Some('p') => Ok(self.check_keyword(1, "rint", TokenType::Print)),
 			Some('r') => Ok(self.check_keyword(1, "eturn", TokenType::Return)),
-			Some('s') => Ok(self.check_keyword(1, "uper", TokenType::Super)),
 			Some('v') => Ok(self.check_keyword(1, "ar", TokenType::Var)),
 			Some('w') => Ok(self.check_keyword(1, "hile", TokenType::While)),
 			
+			Some('c') => {
+				match self.char_from_start(1)? {
+					Some('a') => Ok(self.check_keyword(2, "se", TokenType::Case)),
+					Some('l') => Ok(self.check_keyword(2, "ass", TokenType::Class)),
+					Some('o') => Ok(self.check_keyword(2, "ntinue", TokenType::Continue)),
+					_ => Ok(TokenType::Identifier)
+				}
+			},
+
+			Some('d') => {
+				match self.char_from_start(1)? {
+					Some('e') => Ok(self.check_keyword(2, "fault", TokenType::Default)),
+					Some('o') => Ok(self.check_keyword(2, "", TokenType::Do)),
+					_ => Ok(TokenType::Identifier)
+				}
+			},
+
 			Some('f') => {
-				match self.from_start(1)? {
-					Some('a') => return Ok(self.check_keyword(2, "lse", TokenType::False)),
-					Some('o') => return Ok(self.check_keyword(2, "r", TokenType::For)),
-					Some('u') => return Ok(self.check_keyword(2, "n", TokenType::Fun)),
+				match self.char_from_start(1)? {
+					Some('a') => Ok(self.check_keyword(2, "lse", TokenType::False)),
+					Some('o') => Ok(self.check_keyword(2, "r", TokenType::For)),
+					Some('u') => Ok(self.check_keyword(2, "n", TokenType::Fun)),
+					_ => Ok(TokenType::Identifier)
+				}
+			},
+
+			Some('s') => {
+				match self.char_from_start(1)? {
+					Some('u') => Ok(self.check_keyword(2, "per", TokenType::Super)),
+					Some('w') => Ok(self.check_keyword(2, "itch", TokenType::Switch)),
 					_ => Ok(TokenType::Identifier)
 				}
 			},
 
 			Some('t') => {
-				match self.from_start(1)? {
-					Some('h') => return Ok(self.check_keyword(2, "is", TokenType::This)),
-					Some('r') => return Ok(self.check_keyword(2, "ue", TokenType::True)),
+				match self.char_from_start(1)? {
+					Some('h') => Ok(self.check_keyword(2, "is", TokenType::This)),
+					Some('r') => Ok(self.check_keyword(2, "ue", TokenType::True)),
 					_ => Ok(TokenType::Identifier)
 				}
 			}
@@ -184,27 +316,54 @@ impl<'src> Scanner<'src> {
 					self.line += 1;
 				}
 
-				Some('/') => {
-					if self.peek_next()? == Some('/') {
-						while self.peek()? != Some('\n') && !self.is_at_end() {
-							self.advance();
-						}
-					} else {
-						return Ok(());
+				Some('/') if self.peek_next()? == Some('/') => {
+					while self.peek()? != Some('\n') && !self.is_at_end() {
+						self.advance();
 					}
 				}
 
+				Some('/') if self.peek_next()? == Some('*') => self.block_comment()?,
+
 				_ => return Ok(())
 			}
 		}
 	}
 
+	/// Skips a `/* ... */` comment. Block comments nest, so a region that
+	/// already contains one can be commented out as a whole.
+	fn block_comment(&mut self) -> Result<(), TokenError> {
+		let opening_line = self.line;
+		self.advance();
+		self.advance();
+
+		let mut depth = 1;
+		while depth > 0 {
+			match self.peek()? {
+				None => return Err(TokenError::new(opening_line, "Unterminated block comment.")),
+				Some('/') if self.peek_next()? == Some('*') => {
+					self.advance();
+					depth += 1;
+				}
+				Some('*') if self.peek_next()? == Some('/') => {
+					self.advance();
+					depth -= 1;
+				}
+				Some('\n') => self.line += 1,
+				_ => {}
+			}
+
+			self.advance();
+		}
+
+		Ok(())
+	}
+
 	fn is_digit(&self, c: char) -> bool {
-		c >= '0' && c <= '9'
+		c.is_ascii_digit()
 	}
 
 	fn is_alpha(&self, c: char) -> bool {
-		(c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+		c.is_ascii_alphabetic() || c == '_'
 	}
 
 	fn is_alpha_numeric(&self, c: char) -> bool {
@@ -225,7 +384,7 @@ impl<'src> Scanner<'src> {
 		Ok(next_char)
 	}
 
-	fn from_start(&self, start_offset: usize) -> Result<Option<char>, TokenError> {
+	fn char_from_start(&self, start_offset: usize) -> Result<Option<char>, TokenError> {
         let mut it = self.source[self.start+start_offset..].chars();
 		let next_char = it.next();
 		Ok(next_char)
@@ -263,4 +422,54 @@ impl<'src> Scanner<'src> {
 			line: self.line
 		}
 	}
-}
\ No newline at end of file
+}
+
+/// Replaces the escape sequences in the contents of a string literal.
+pub fn unescape(raw: &str) -> Result<String, &'static str> {
+	let mut unescaped = String::with_capacity(raw.len());
+	let mut chars = raw.chars();
+	while let Some(c) = chars.next() {
+		if c == '\\' {
+			unescaped.push(escape(&mut chars)?);
+		} else {
+			unescaped.push(c);
+		}
+	}
+
+	Ok(unescaped)
+}
+
+/// Decodes the escape sequence after a backslash, consuming it from
+/// `chars`: one of `\n \t \r \\ \" \0` or `\u{XXXX}` with one to six hex
+/// digits.
+fn escape(chars: &mut Chars) -> Result<char, &'static str> {
+	match chars.next() {
+		Some('n') => Ok('\n'),
+		Some('t') => Ok('\t'),
+		Some('r') => Ok('\r'),
+		Some('\\') => Ok('\\'),
+		Some('"') => Ok('"'),
+		Some('0') => Ok('\0'),
+		Some('u') => {
+			if chars.next() != Some('{') {
+				return Err("Expect '{' after '\\u'.");
+			}
+
+			let mut code_point = 0;
+			let mut digits = 0;
+			loop {
+				match chars.next() {
+					Some('}') if digits > 0 => break,
+					Some(c) if digits < 6 && c.is_ascii_hexdigit() => {
+						code_point = code_point * 16 + c.to_digit(16).unwrap_or(0);
+						digits += 1;
+					}
+					_ => return Err("Invalid Unicode escape sequence."),
+				}
+			}
+
+			char::from_u32(code_point).ok_or("Invalid Unicode code point.")
+		}
+		_ => Err("Invalid escape sequence."),
+	}
+}
@@ -2,20 +2,24 @@
 pub enum TokenType {
     // Single-character tokens.
     LeftParen, RightParen, LeftBrace, RightBrace,
-    Comma, Dot, Minus, Plus, SemiColon, Slash, Star,
+    Comma, Dot, Minus, Plus, SemiColon, Slash, Star, Colon, Question,
+    Percent, Ampersand, Pipe, Caret,
 
     // One or two character tokens.
     Bang, BangEqual,
+    StarStar, Tilde, TildeSlash,
+    PlusEqual, MinusEqual, StarEqual, SlashEqual, PercentEqual,
+    PlusPlus, MinusMinus,
     Equal, EqualEqual,
-    Greater, GreaterEqual,
-    Less, LessEqual,
+    Greater, GreaterEqual, GreaterGreater,
+    Less, LessEqual, LessLess,
 
     // Literals.
     Identifier, String, Number,
 
     // Keywords.
-    And, Class, Else, False, Fun, For, If, Nil, Or,
-    Print, Return, Super, This, True, Var, While,
+    And, Break, Case, Class, Continue, Default, Do, Else, False, Fun, For, If, Nil, Or,
+    Print, Return, Super, Switch, This, True, Var, While,
 
     EOF
 }
@@ -1,11 +1,126 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use std::rc::Rc;
 
-/// The heap-allocated kinds of objects (strings for now).
-#[derive(Debug, Clone, PartialEq)]
+use crate::bigint::BigInt;
+use crate::chunk::Chunk;
+use crate::error::RuntimeError;
+use crate::memory::{GcRef, Heap};
+
+/// The heap-allocated kinds of objects. They live in the VM's `Heap` and
+/// refer to each other through `GcRef` handles. Strings are interned, so two
+/// string handles are equal exactly when their contents are.
+#[derive(Debug)]
 pub enum Obj {
-    String(String),
-    // Future: Function(FunctionData), Instance(InstanceData), etc.
+    String(Rc<str>),
+    /// An integer too wide for the inline payload of a NaN-boxed `Value`.
+    /// The default representation holds every `i64` inline and never
+    /// creates one.
+    Int(i64),
+    BigInt(BigInt),
+    Function(Function),
+    Native(Native),
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+}
+
+/// A compiled function. The top-level script is a function without a name.
+#[derive(Debug)]
+pub struct Function {
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+    pub name: Option<String>,
+}
+
+impl Function {
+    pub fn new(name: Option<String>) -> Self {
+        Self {
+            arity: 0,
+            upvalue_count: 0,
+            chunk: Chunk::new(),
+            name,
+        }
+    }
+}
+
+/// A function together with the variables it captured. `function` always
+/// refers to an `Obj::Function` and each upvalue to an `Obj::Upvalue`.
+#[derive(Debug)]
+pub struct Closure {
+    pub function: GcRef,
+    pub upvalues: Vec<GcRef>,
+}
+
+/// A captured variable. It points at a VM stack slot while the variable is
+/// still live on the stack, and owns the value once that slot is popped.
+#[derive(Debug, PartialEq)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+/// A class. `name` and the method names refer to `Obj::String`s and each
+/// method to the `Obj::Closure` implementing it.
+#[derive(Debug)]
+pub struct Class {
+    pub name: GcRef,
+    pub methods: HashMap<GcRef, GcRef>,
+}
+
+impl Class {
+    pub fn new(name: GcRef) -> Self {
+        Self {
+            name,
+            methods: HashMap::new(),
+        }
+    }
+}
+
+/// An instance of a class. `class` always refers to an `Obj::Class` and
+/// field names to `Obj::String`s.
+#[derive(Debug)]
+pub struct Instance {
+    pub class: GcRef,
+    pub fields: HashMap<GcRef, Value>,
+}
+
+impl Instance {
+    pub fn new(class: GcRef) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+}
+
+/// A method closure paired with the instance it was read from.
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: GcRef,
+}
+
+pub type NativeFn = dyn Fn(&mut Heap, &[Value]) -> Result<Value, RuntimeError>;
+
+/// A function implemented in Rust and exposed to Lox as a global.
+pub struct Native {
+    pub name: String,
+    pub arity: usize,
+    pub function: Rc<NativeFn>,
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Obj {
@@ -14,62 +129,525 @@ impl Obj {
     }
 
     pub fn as_string(&self) -> Option<&str> {
-        let Obj::String(s) = self;
-        Some(s.as_str())
+        match self {
+            Obj::String(s) => Some(s),
+            _ => None,
+        }
     }
 
-    /// Concatenate two Obj::String values. Accepts references to `Rc<Obj>`
-    /// (which is what Value::Obj stores). Returns `Some(Value)` when both
-    /// operands are strings, otherwise `None`.
-    pub fn concat_strings(a: &Rc<Obj>, b: &Rc<Obj>) -> Option<Value> {
-        match (a.as_string(), b.as_string()) {
-            (Some(sa), Some(sb)) => {
-                Some(Value::obj(Obj::String(format!("{}{}", sa, sb))))
-            }
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Obj::Int(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bigint(&self) -> Option<&BigInt> {
+        match self {
+            Obj::BigInt(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_function(&self) -> Option<&Function> {
+        match self {
+            Obj::Function(function) => Some(function),
+            _ => None,
+        }
+    }
+
+    pub fn as_closure(&self) -> Option<&Closure> {
+        match self {
+            Obj::Closure(closure) => Some(closure),
+            _ => None,
+        }
+    }
+
+    pub fn as_upvalue(&self) -> Option<&Upvalue> {
+        match self {
+            Obj::Upvalue(upvalue) => Some(upvalue),
+            _ => None,
+        }
+    }
+
+    pub fn as_class(&self) -> Option<&Class> {
+        match self {
+            Obj::Class(class) => Some(class),
+            _ => None,
+        }
+    }
+
+    pub fn as_instance(&self) -> Option<&Instance> {
+        match self {
+            Obj::Instance(instance) => Some(instance),
             _ => None,
         }
     }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Obj::String(_) => "string",
+            Obj::Int(_) => "int",
+            Obj::BigInt(_) => "bigint",
+            Obj::Function(_) | Obj::Native(_) | Obj::Closure(_) | Obj::BoundMethod(_) => "function",
+            Obj::Upvalue(_) => "upvalue",
+            Obj::Class(_) => "class",
+            Obj::Instance(_) => "instance",
+        }
+    }
 }
 
-/// The VM value: small values are stored directly; bigger ones are Rc<Obj>.
-#[derive(Debug, Clone, PartialEq)]
+/// The VM value: small values are stored directly; objects live on the heap.
+/// Numbers are either 64-bit integers or floats. `Undefined` marks global
+/// slots that have no value yet and never reaches Lox code.
+///
+/// Integers the representation can't hold inline are boxed as `Obj::Int`,
+/// so code that makes integers of any size goes through `Value::int_in` or
+/// `checked_int`, and reads them back with the heap at hand.
+///
+/// Code outside this module builds values with the constructors and reads
+/// them with the `as_*`/`is_*` accessors, so that it also works with the
+/// NaN-boxed representation of the `nan_boxing` feature.
+#[cfg(not(feature = "nan_boxing"))]
+#[derive(Debug, Clone, Copy)]
 pub enum Value {
     Bool(bool),
     Nil,
+    Int(i64),
     Number(f64),
-    Obj(Rc<Obj>),
+    Obj(GcRef),
+    Undefined,
 }
 
+#[cfg(not(feature = "nan_boxing"))]
 impl Value {
+    const INT_MIN: i64 = i64::MIN;
+    const INT_MAX: i64 = i64::MAX;
+
     pub fn bool_val(b: bool) -> Self {
         Value::Bool(b)
     }
     pub fn nil() -> Self {
         Value::Nil
     }
+    /// An integer value. `n` must lie within the range `checked_int`
+    /// accepts.
+    pub fn int(n: i64) -> Self {
+        Value::Int(n)
+    }
     pub fn number(n: f64) -> Self {
         Value::Number(n)
     }
-    pub fn obj(o: Obj) -> Self {
-        Value::Obj(Rc::new(o))
+    pub fn obj(o: GcRef) -> Self {
+        Value::Obj(o)
+    }
+    pub fn undefined() -> Self {
+        Value::Undefined
+    }
+
+    pub fn as_bool(self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(self) -> Option<i64> {
+        match self {
+            Value::Int(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_obj(self) -> Option<GcRef> {
+        match self {
+            Value::Obj(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn is_nil(self) -> bool {
+        matches!(self, Value::Nil)
+    }
+
+    pub fn is_undefined(self) -> bool {
+        matches!(self, Value::Undefined)
+    }
+}
+
+#[cfg(not(feature = "nan_boxing"))]
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Nil, Value::Nil) | (Value::Undefined, Value::Undefined) => true,
+            (Value::Obj(a), Value::Obj(b)) => a == b,
+            _ => self.compare_numbers(*other) == Some(Ordering::Equal),
+        }
+    }
+}
+
+/// The VM value packed into the 64 bits of an `f64`. Floats are stored as
+/// themselves. Every other value is a quiet NaN: the singletons use the low
+/// bits as a tag, integers set `INT_TAG` and keep a 49-bit two's complement
+/// payload below it, and objects set the sign bit and keep their heap slot
+/// in the low bits. Wider integers are boxed as `Obj::Int`.
+#[cfg(feature = "nan_boxing")]
+#[derive(Clone, Copy)]
+pub struct Value(u64);
+
+#[cfg(feature = "nan_boxing")]
+const SIGN_BIT: u64 = 0x8000_0000_0000_0000;
+#[cfg(feature = "nan_boxing")]
+const QNAN: u64 = 0x7ffc_0000_0000_0000;
+
+#[cfg(feature = "nan_boxing")]
+const TAG_NIL: u64 = 1;
+#[cfg(feature = "nan_boxing")]
+const TAG_FALSE: u64 = 2;
+#[cfg(feature = "nan_boxing")]
+const TAG_TRUE: u64 = 3;
+#[cfg(feature = "nan_boxing")]
+const TAG_UNDEFINED: u64 = 4;
+
+#[cfg(feature = "nan_boxing")]
+const INT_TAG: u64 = 1 << 49;
+#[cfg(feature = "nan_boxing")]
+const INT_PAYLOAD: u64 = INT_TAG - 1;
+
+#[cfg(feature = "nan_boxing")]
+impl Value {
+    const INT_MIN: i64 = -(1 << 48);
+    const INT_MAX: i64 = (1 << 48) - 1;
+
+    pub fn bool_val(b: bool) -> Self {
+        Value(QNAN | if b { TAG_TRUE } else { TAG_FALSE })
+    }
+    pub fn nil() -> Self {
+        Value(QNAN | TAG_NIL)
+    }
+    /// An integer value. `n` must lie within the range `checked_int`
+    /// accepts.
+    pub fn int(n: i64) -> Self {
+        Value(QNAN | INT_TAG | (n as u64 & INT_PAYLOAD))
+    }
+    pub fn number(n: f64) -> Self {
+        // Any NaN the program computes is stored as the canonical one, whose
+        // bits can't be mistaken for a boxed value.
+        if n.is_nan() {
+            return Value(f64::NAN.to_bits());
+        }
+        Value(n.to_bits())
+    }
+    pub fn obj(o: GcRef) -> Self {
+        Value(SIGN_BIT | QNAN | o.to_index() as u64)
+    }
+    pub fn undefined() -> Self {
+        Value(QNAN | TAG_UNDEFINED)
+    }
+
+    pub fn as_bool(self) -> Option<bool> {
+        match self.0 {
+            bits if bits == QNAN | TAG_TRUE => Some(true),
+            bits if bits == QNAN | TAG_FALSE => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(self) -> Option<i64> {
+        if self.0 & (SIGN_BIT | QNAN | INT_TAG) != QNAN | INT_TAG {
+            return None;
+        }
+        // Move the payload's sign bit up to bit 63 and shift back to extend it.
+        Some(((self.0 & INT_PAYLOAD) << 15) as i64 >> 15)
+    }
+
+    pub fn as_number(self) -> Option<f64> {
+        if self.0 & QNAN == QNAN {
+            return None;
+        }
+        Some(f64::from_bits(self.0))
+    }
+
+    pub fn as_obj(self) -> Option<GcRef> {
+        if self.0 & (SIGN_BIT | QNAN) != SIGN_BIT | QNAN {
+            return None;
+        }
+        Some(GcRef::from_index((self.0 & !(SIGN_BIT | QNAN)) as usize))
+    }
+
+    pub fn is_nil(self) -> bool {
+        self.0 == QNAN | TAG_NIL
+    }
+
+    pub fn is_undefined(self) -> bool {
+        self.0 == QNAN | TAG_UNDEFINED
+    }
+}
+
+#[cfg(feature = "nan_boxing")]
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        // Compare numbers by value so that NaN != NaN, 0 == -0 and 1 == 1.0.
+        // Boxed integers are objects and compare by identity here.
+        if self.as_float().is_some() && other.as_float().is_some() {
+            return self.compare_numbers(*other) == Some(Ordering::Equal);
+        }
+        self.0 == other.0
     }
 }
 
-impl fmt::Display for Obj {
+#[cfg(feature = "nan_boxing")]
+impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Obj::String(s) => write!(f, "{}", s),
+        if let Some(n) = self.as_number() {
+            write!(f, "Number({:?})", n)
+        } else if let Some(n) = self.as_int() {
+            write!(f, "Int({:?})", n)
+        } else if let Some(o) = self.as_obj() {
+            write!(f, "Obj({:?})", o)
+        } else if let Some(b) = self.as_bool() {
+            write!(f, "Bool({:?})", b)
+        } else if self.is_nil() {
+            write!(f, "Nil")
+        } else {
+            write!(f, "Undefined")
+        }
+    }
+}
+
+impl Value {
+    /// An integer value held inline, or `None` if the representation needs
+    /// to box `n` on the heap.
+    pub fn checked_int(n: i64) -> Option<Self> {
+        (Self::INT_MIN..=Self::INT_MAX).contains(&n).then(|| Self::int(n))
+    }
+
+    /// An integer value of any size, boxed on `heap` if it can't be held
+    /// inline. Like `Heap::alloc`, this never collects.
+    pub fn int_in(n: i64, heap: &mut Heap) -> Self {
+        Self::checked_int(n).unwrap_or_else(|| Value::obj(heap.alloc(Obj::Int(n))))
+    }
+
+    /// The value as a float if it is an inline number of either kind.
+    pub fn as_float(self) -> Option<f64> {
+        self.as_int().map(|n| n as f64).or(self.as_number())
+    }
+
+    /// Orders two inline numbers of either kind by their exact values, so
+    /// that an integer and a float compare without rounding the integer.
+    /// Returns `None` if either value is not an inline number, or is NaN.
+    pub fn compare_numbers(self, other: Value) -> Option<Ordering> {
+        match (self.as_int(), other.as_int()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            (Some(a), None) => compare_int_float(a, other.as_number()?),
+            (None, Some(b)) => compare_int_float(b, self.as_number()?).map(Ordering::reverse),
+            (None, None) => self.as_number()?.partial_cmp(&other.as_number()?),
+        }
+    }
+
+    pub fn type_name(&self, heap: &Heap) -> &'static str {
+        if let Some(o) = self.as_obj() {
+            heap.get(o).type_name()
+        } else if self.as_int().is_some() {
+            "int"
+        } else if self.as_number().is_some() {
+            "number"
+        } else if self.as_bool().is_some() {
+            "bool"
+        } else if self.is_nil() {
+            "nil"
+        } else {
+            "undefined"
+        }
+    }
+
+    /// Formats the value the way `print` shows it, looking objects up in
+    /// `heap`.
+    pub fn display(self, heap: &Heap) -> ValueDisplay<'_> {
+        ValueDisplay { value: self, heap }
+    }
+}
+
+/// Orders an integer and a float by their exact values. Returns `None` if
+/// `f` is NaN.
+pub(crate) fn compare_int_float(i: i64, f: f64) -> Option<Ordering> {
+    // -2^63 is the smallest i64 and 2^63 is one past the largest. Floats in
+    // between truncate to an i64 exactly; those outside compare by sign.
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    if f.is_nan() {
+        return None;
+    }
+    if f >= LIMIT {
+        return Some(Ordering::Less);
+    }
+    if f < -LIMIT {
+        return Some(Ordering::Greater);
+    }
+
+    let whole = f.trunc();
+    Some(i.cmp(&(whole as i64)).then(0.0.partial_cmp(&(f - whole))?))
+}
+
+pub struct ValueDisplay<'heap> {
+    value: Value,
+    heap: &'heap Heap,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "<fn {}>", name),
+            None => write!(f, "<script>"),
         }
     }
 }
 
-impl fmt::Display for Value {
+impl fmt::Display for ValueDisplay<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Value::Bool(b) => write!(f, "{}", b),
-            Value::Nil => write!(f, "nil"),
-            Value::Number(n) => write!(f, "{}", n),
-            Value::Obj(o) => write!(f, "{}", o),
+        let o = match self.value.as_obj() {
+            Some(o) => o,
+            None if self.value.is_nil() => return write!(f, "nil"),
+            None if self.value.is_undefined() => return write!(f, "undefined"),
+            None => match (self.value.as_int(), self.value.as_number(), self.value.as_bool()) {
+                (Some(n), _, _) => return write!(f, "{}", n),
+                (_, Some(n), _) => return write!(f, "{}", n),
+                (_, _, Some(b)) => return write!(f, "{}", b),
+                _ => unreachable!("Value must be a number or a bool"),
+            },
+        };
+
+        match self.heap.get(o) {
+            Obj::String(s) => write!(f, "{}", s),
+            Obj::Int(n) => write!(f, "{}", n),
+            Obj::BigInt(n) => write!(f, "{}", n),
+            Obj::Function(function) => write!(f, "{}", function),
+            Obj::Native(native) => write!(f, "<native fn {}>", native.name),
+            Obj::Closure(closure) => write!(f, "{}", Value::obj(closure.function).display(self.heap)),
+            Obj::Upvalue(_) => write!(f, "upvalue"),
+            Obj::Class(class) => write!(f, "{}", Value::obj(class.name).display(self.heap)),
+            Obj::BoundMethod(bound) => write!(f, "{}", Value::obj(bound.method).display(self.heap)),
+            Obj::Instance(instance) => match self.heap.get(instance.class) {
+                Obj::Class(class) => write!(f, "{} instance", Value::obj(class.name).display(self.heap)),
+                _ => write!(f, "instance"),
+            },
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ints_round_trip() {
+        for n in [0, 1, -1, 42, -42, Value::INT_MIN, Value::INT_MAX] {
+            let value = Value::int(n);
+            assert_eq!(value.as_int(), Some(n));
+            assert_eq!(value.as_number(), None);
+            assert_eq!(value.as_obj(), None);
+            assert_eq!(value.as_bool(), None);
+            assert!(!value.is_nil() && !value.is_undefined());
+        }
+    }
+
+    #[test]
+    fn every_i64_round_trips_through_the_heap() {
+        let mut heap = Heap::new();
+        // Both sides of the NaN-boxed payload limits, and the ends of the range.
+        for n in [i64::MIN, -(1 << 48) - 1, -(1 << 48), 0, -1, (1 << 48) - 1, 1 << 48, i64::MAX] {
+            let value = Value::int_in(n, &mut heap);
+            let boxed = value.as_obj().and_then(|o| heap.get(o).as_int());
+            assert_eq!(value.as_int().or(boxed), Some(n));
+            assert_eq!(value.type_name(&heap), "int");
+            assert_eq!(value.display(&heap).to_string(), n.to_string());
+        }
+    }
+
+    #[test]
+    fn floats_round_trip() {
+        let floats = [0.0, -0.0, 1.5, -1.5, f64::INFINITY, f64::NEG_INFINITY, f64::MAX, f64::MIN, f64::MIN_POSITIVE, 5e-324];
+        for n in floats {
+            let value = Value::number(n);
+            assert_eq!(value.as_number().map(f64::to_bits), Some(n.to_bits()));
+            assert_eq!(value.as_int(), None);
+            assert_eq!(value.as_obj(), None);
+            assert_eq!(value.as_bool(), None);
+        }
+    }
+
+    #[test]
+    fn nans_stay_floats() {
+        // NaNs with a sign or payload must not be read back as a boxed value.
+        let nans = [f64::NAN, -f64::NAN, f64::from_bits(0x7ff0_0000_0000_0001), f64::from_bits(0xffff_ffff_ffff_ffff)];
+        for n in nans {
+            let value = Value::number(n);
+            assert!(value.as_number().is_some_and(f64::is_nan));
+            assert_eq!(value.as_int(), None);
+            assert_eq!(value.as_obj(), None);
+            assert_eq!(value.as_bool(), None);
+            assert!(!value.is_nil() && !value.is_undefined());
+        }
+    }
+
+    #[test]
+    fn singletons_are_distinct() {
+        let values = [Value::nil(), Value::bool_val(true), Value::bool_val(false), Value::undefined()];
+        assert!(values[0].is_nil());
+        assert_eq!(values[1].as_bool(), Some(true));
+        assert_eq!(values[2].as_bool(), Some(false));
+        assert!(values[3].is_undefined());
+
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                assert_eq!(a == b, i == j);
+            }
+        }
+    }
+
+    #[test]
+    fn objects_round_trip() {
+        let mut heap = Heap::new();
+        let a = heap.intern("a");
+        let b = heap.intern("b");
+        assert_eq!(Value::obj(a).as_obj(), Some(a));
+        assert_eq!(Value::obj(b).as_obj(), Some(b));
+        assert_eq!(Value::obj(a).as_int(), None);
+        assert_eq!(Value::obj(a).as_number(), None);
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(Value::number(0.0), Value::number(-0.0));
+        assert_ne!(Value::number(f64::NAN), Value::number(f64::NAN));
+        assert_eq!(Value::int(1), Value::number(1.0));
+        assert_ne!(Value::int(1), Value::number(1.5));
+        assert_eq!(Value::int(-3).compare_numbers(Value::number(-2.5)), Some(Ordering::Less));
+    }
+
+    #[cfg(feature = "nan_boxing")]
+    #[test]
+    fn nan_boxed_values_are_eight_bytes() {
+        assert_eq!(std::mem::size_of::<Value>(), 8);
+    }
+
+    #[cfg(feature = "nan_boxing")]
+    #[test]
+    fn large_object_indices_round_trip() {
+        for index in [0, 1, u32::MAX as usize, (1 << 48) + 7, (1 << 50) - 1] {
+            let reference = GcRef::from_index(index);
+            let value = Value::obj(reference);
+            assert_eq!(value.as_obj(), Some(reference));
+            assert_eq!(value.as_int(), None);
+            assert_eq!(value.as_number(), None);
+            assert!(!value.is_nil() && !value.is_undefined());
+        }
+    }
+}
@@ -1,44 +1,249 @@
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::rc::Rc;
 
 use num_traits::FromPrimitive;
 
-use std::collections::HashMap;
-
-use crate::chunk::{Chunk, OpCode};
-use crate::compiler::Compiler;
+use crate::bigint::BigInt;
+use crate::chunk::OpCode;
+use crate::compiler::{Compiler, GlobalNames};
 use crate::error::{RLoxError, RuntimeError};
-use crate::value::{Obj, Value};
+use crate::natives;
+use crate::memory::{GcRef, Heap};
+use crate::value::{self, BoundMethod, Class, Closure, Function, Instance, Native, NativeFn, Obj, Upvalue, Value};
 
-pub struct VM {
-	chunk: Rc<RefCell<Chunk>>,
+const FRAMES_MAX: usize = 64;
+const STACK_MAX: usize = FRAMES_MAX * (u8::MAX as usize + 1);
+/// The largest magnitude up to which a double holds every integer exactly.
+const INTEGER_MAX: f64 = (1u64 << 53) as f64;
+/// The most bits a bigint power may take, so that a large exponent fails
+/// up front instead of exhausting memory.
+const BIGINT_POWER_BITS_MAX: u64 = 1 << 20;
+
+/// An ongoing function call. `slots` is the index of the first stack slot
+/// the function can use, which holds the closure itself. The closure's
+/// function and bytecode are cached to avoid a heap lookup per byte.
+struct CallFrame {
+	closure: GcRef,
+	function: GcRef,
 	code: Rc<RefCell<Vec<u8>>>,
-	globals: HashMap<String, Value>,
 	ip: usize,
+	slots: usize,
+}
+
+/// The operands of an arithmetic instruction. Integers stay integers only
+/// if both operands are; otherwise both are promoted to floats. A bigint
+/// promotes an integer to a bigint, and is itself promoted to a float.
+enum Operands {
+	Int(i64, i64),
+	Float(f64, f64),
+	Big(BigInt, BigInt),
+}
+
+impl Operands {
+	fn of(a: Value, b: Value, heap: &Heap) -> Option<Self> {
+		Some(match (Number::of(a, heap)?, Number::of(b, heap)?) {
+			(Number::Int(a), Number::Int(b)) => Operands::Int(a, b),
+			(Number::Big(a), Number::Big(b)) => Operands::Big(a, b),
+			(Number::Big(a), Number::Int(b)) => Operands::Big(a, BigInt::from(b)),
+			(Number::Int(a), Number::Big(b)) => Operands::Big(BigInt::from(a), b),
+			(a, b) => Operands::Float(a.to_f64(), b.to_f64()),
+		})
+	}
+}
+
+/// A number of any kind, read from a value and the heap it may be boxed on.
+enum Number {
+	Int(i64),
+	Float(f64),
+	Big(BigInt),
+}
+
+impl Number {
+	fn of(value: Value, heap: &Heap) -> Option<Self> {
+		if let Some(n) = value.as_int() {
+			return Some(Number::Int(n));
+		}
+		if let Some(n) = value.as_number() {
+			return Some(Number::Float(n));
+		}
+
+		match heap.get(value.as_obj()?) {
+			Obj::Int(n) => Some(Number::Int(*n)),
+			Obj::BigInt(n) => Some(Number::Big(n.clone())),
+			_ => None,
+		}
+	}
+
+	fn to_f64(&self) -> f64 {
+		match self {
+			Number::Int(n) => *n as f64,
+			Number::Float(n) => *n,
+			Number::Big(n) => n.to_f64(),
+		}
+	}
+
+	/// Orders two numbers by their exact values. NaN is unordered.
+	fn compare(&self, other: &Number) -> Option<Ordering> {
+		match (self, other) {
+			(Number::Int(a), Number::Int(b)) => Some(a.cmp(b)),
+			(Number::Float(a), Number::Float(b)) => a.partial_cmp(b),
+			(Number::Big(a), Number::Big(b)) => Some(a.cmp(b)),
+			(Number::Int(a), Number::Float(b)) => value::compare_int_float(*a, *b),
+			(Number::Big(a), Number::Int(b)) => Some(a.cmp(&BigInt::from(*b))),
+			(Number::Big(a), Number::Float(b)) => a.partial_cmp_f64(*b),
+			_ => other.compare(self).map(Ordering::reverse),
+		}
+	}
+}
+
+/// The virtual machine. Globals live in the slots `global_names` assigns
+/// them; a slot holds `Value::undefined()` until its global is defined.
+pub struct VM {
+	frames: Vec<CallFrame>,
+	globals: Vec<Value>,
+	global_names: GlobalNames,
+	open_upvalues: Vec<GcRef>,
 	stack: Vec<Value>,
+	heap: Heap,
+	init_string: GcRef,
 	instruction_line: usize,
 }
 
+impl Default for VM {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 impl VM {
-	pub fn interpret(source: &str) -> Result<(), RLoxError> {
-		let mut compiler = Compiler::new(source);
-		let res = compiler.compile()?;
-		let chunk = compiler.current_chunk;
+	/// Creates a VM with the default builtins (`clock`, `type`, `str`, `num`)
+	/// already defined as globals.
+	pub fn new() -> Self {
+		let mut heap = Heap::new();
+		let init_string = heap.intern("init");
 
 		let mut vm = VM {
-			chunk: Rc::clone(&chunk),
-			code: Rc::clone(&chunk.borrow().code),
-			globals: HashMap::new(),
-			ip: 0,
-			stack: Vec::with_capacity(256),
+			frames: Vec::with_capacity(FRAMES_MAX),
+			globals: Vec::new(),
+			global_names: GlobalNames::new(),
+			open_upvalues: Vec::new(),
+			stack: Vec::with_capacity(STACK_MAX),
+			heap,
+			init_string,
 			instruction_line: 0,
 		};
 
-		if res {
-			vm.run()?;
+		vm.define_native("clock", 0, natives::clock);
+		vm.define_native("type", 1, natives::type_of);
+		vm.define_native("str", 1, natives::str);
+		vm.define_native("num", 1, natives::num);
+		vm
+	}
+
+	/// Exposes a Rust function to Lox as a global called `name`. The VM
+	/// checks the argument count against `arity` before calling it.
+	pub fn define_native<F>(&mut self, name: &str, arity: usize, function: F)
+	where
+		F: Fn(&mut Heap, &[Value]) -> Result<Value, RuntimeError> + 'static,
+	{
+		let native = self.alloc(Obj::Native(Native {
+			name: name.to_string(),
+			arity,
+			function: Rc::new(function),
+		}));
+
+		// Keep the native reachable while its name is interned.
+		self.stack.push(Value::obj(native));
+		let name = self.intern(name);
+		if let Some(slot) = self.global_names.resolve(name) {
+			self.globals.resize(self.global_names.len(), Value::undefined());
+			self.globals[slot as usize] = Value::obj(native);
 		}
+		self.stack.pop();
+	}
 
-		Ok(())
+	/// Sets how far the heap may grow past the live data left by the last
+	/// collection before collecting again. Defaults to 2.
+	pub fn set_gc_grow_factor(&mut self, factor: usize) {
+		self.heap.set_grow_factor(factor);
+	}
+
+	/// Compiles and runs `source`. Globals persist between calls.
+	pub fn interpret(&mut self, source: &str) -> Result<(), RLoxError> {
+		let mut compiler = Compiler::new(source, &mut self.heap, &mut self.global_names);
+		let function = match compiler.compile()? {
+			Some(function) => self.heap.alloc(Obj::Function(function)),
+			None => return Ok(()),
+		};
+
+		// Make room for any globals the new code mentioned for the first time.
+		self.globals.resize(self.global_names.len(), Value::undefined());
+
+		// Keep the function reachable while its closure is allocated.
+		self.stack.push(Value::obj(function));
+		let closure = self.alloc(Obj::Closure(Closure { function, upvalues: Vec::new() }));
+		self.pop()?;
+
+		self.stack.push(Value::obj(closure));
+		let result = self.call(closure, 0).and_then(|_| self.run());
+		if result.is_err() {
+			self.reset_stack();
+		}
+
+		result
+	}
+
+	/// Allocates `obj` on the heap, collecting garbage first if the heap has
+	/// grown past its threshold. Everything the caller still needs must be
+	/// reachable from the stack, globals or call frames at this point.
+	fn alloc(&mut self, obj: Obj) -> GcRef {
+		if self.heap.should_collect() {
+			self.collect_garbage();
+		}
+
+		self.heap.alloc(obj)
+	}
+
+	/// Interns `chars`, collecting garbage first like `alloc` does.
+	fn intern(&mut self, chars: &str) -> GcRef {
+		if self.heap.should_collect() {
+			self.collect_garbage();
+		}
+
+		self.heap.intern(chars)
+	}
+
+	fn collect_garbage(&mut self) {
+		for value in &self.stack {
+			self.heap.mark_value(*value);
+		}
+
+		for value in &self.globals {
+			self.heap.mark_value(*value);
+		}
+
+		for name in self.global_names.names() {
+			self.heap.mark_object(*name);
+		}
+
+		for frame in &self.frames {
+			self.heap.mark_object(frame.closure);
+		}
+
+		for upvalue in &self.open_upvalues {
+			self.heap.mark_object(*upvalue);
+		}
+
+		self.heap.mark_object(self.init_string);
+
+		self.heap.collect();
+	}
+
+	fn reset_stack(&mut self) {
+		self.stack.clear();
+		self.frames.clear();
+		self.open_upvalues.clear();
 	}
 
 	fn run(&mut self) -> Result<(), RLoxError> {
@@ -46,26 +251,50 @@ impl VM {
 			#[cfg(feature = "debug_trace_execution")]
 			{
 				use crate::debug::Disassemble;
-				println!("{:?}", self.stack);
-				Disassemble::instruction(&*self.chunk.borrow(), self.ip)?;
+				for value in &self.stack {
+					print!("[ {} ]", value.display(&self.heap));
+				}
+				println!();
+				let frame = self.frame()?;
+				Disassemble::instruction(&self.function(frame.function).chunk, frame.ip, &self.heap)?;
 			}
 
 			self.instruction_line = self.current_line().unwrap_or(0);
 			let instruction = self.read_byte()?;
 			match OpCode::from_u8(instruction) {
 				Some(OpCode::OpReturn) => {
-					return Ok(());
+					let result = self.pop()?;
+					let frame = self.frames.pop()
+						.ok_or(RuntimeError::new(self.instruction_line, "No call frame to return from"))?;
+
+					self.close_upvalues(frame.slots);
+					if self.frames.is_empty() {
+						self.pop()?;
+						return Ok(());
+					}
+
+					self.stack.truncate(frame.slots);
+					self.stack.push(result);
+				}
+
+				Some(OpCode::OpCall) => {
+					let arg_count = self.read_byte()?;
+					let callee = *self.peek(arg_count as usize)?;
+					self.call_value(callee, arg_count)?;
 				}
 
 				Some(OpCode::OpPrint) => {
-					println!("{}", self.pop()?);
+					let value = self.pop()?;
+					println!("{}", value.display(&self.heap));
 				}
 
 				Some(OpCode::OpNegate) => {
 					let value = self.pop()?;
-					let res = match value {
-						Value::Number(num) => Ok(Value::Number(-num)),
-						_ => {
+					let res = match Number::of(value, &self.heap) {
+						Some(Number::Int(n)) => self.int_result(n.checked_neg()),
+						Some(Number::Float(n)) => Ok(Value::number(-n)),
+						Some(Number::Big(n)) => Ok(self.big_result(-&n)),
+						None => {
 							self.stack.push(value);
 							Err(RuntimeError::new(self.instruction_line, "Cannot negate non number"))
 						}
@@ -77,179 +306,441 @@ impl VM {
 					let b = self.pop()?;
 					let a = self.pop()?;
 
-					self.stack.push(
-						match (a, b) {
-							(Value::Number(a), Value::Number(b)) => {
-								Ok(Value::Number(a + b))
-							}
-							(Value::Obj(a), Value::Obj(b)) => {
-								Obj::concat_strings(&a, &b).ok_or(RuntimeError::new(
-									self.instruction_line,
-									"Operands must be two numbers or two strings.",
-								))
-							}
-							_ => Err(RuntimeError::new(
+					let result = match Operands::of(a, b, &self.heap) {
+						Some(Operands::Int(a, b)) => self.int_result(a.checked_add(b))?,
+						Some(Operands::Float(a, b)) => Value::number(a + b),
+						Some(Operands::Big(a, b)) => self.big_result(&a + &b),
+						None => match (a.as_obj(), b.as_obj()) {
+							(Some(a), Some(b)) => self.concatenate(a, b)?,
+							_ => return Err(RuntimeError::new(
 								self.instruction_line,
 								"Operands must be two numbers or two strings.",
-							)),
-						}?,
-					);
+							).into()),
+						},
+					};
+					self.stack.push(result);
 				}
 
 				Some(OpCode::OpSubtract) => {
-					let b = self.pop()?;
-					let a = self.pop()?;
-
-					self.stack.push(match (a, b) {
-						(Value::Number(a), Value::Number(b)) => {
-							Ok(Value::Number(a - b))
-						}
-						_ => Err(RuntimeError::new(
-							self.instruction_line,
-							"Operands must be two numbers.",
-						)),
-					}?);
+					let result = match self.numeric_operands()? {
+						Operands::Int(a, b) => self.int_result(a.checked_sub(b))?,
+						Operands::Float(a, b) => Value::number(a - b),
+						Operands::Big(a, b) => self.big_result(&a - &b),
+					};
+					self.stack.push(result);
 				}
 
 				Some(OpCode::OpDivide) => {
-					let b = self.pop()?;
-					let a = self.pop()?;
+					// Always a true division; `~/` divides integers.
+					let result = match self.numeric_operands()? {
+						Operands::Int(a, b) => Value::number(a as f64 / b as f64),
+						Operands::Float(a, b) => Value::number(a / b),
+						Operands::Big(a, b) => Value::number(a.to_f64() / b.to_f64()),
+					};
+					self.stack.push(result);
+				}
 
-					self.stack.push(match (a, b) {
-						(Value::Number(a), Value::Number(b)) => {
-							Ok(Value::Number(a / b))
-						}
-						_ => Err(RuntimeError::new(
-							self.instruction_line,
-							"Operands must be two numbers.",
-						)),
-					}?);
+				Some(OpCode::OpModulo) => {
+					// Like C's `%` and `fmod`: the result takes the sign of `a`.
+					let result = match self.numeric_operands()? {
+						Operands::Int(_, 0) => return Err(self.division_by_zero().into()),
+						Operands::Int(a, b) => self.int_result(Some(a.wrapping_rem(b)))?,
+						Operands::Float(a, b) => Value::number(a % b),
+						Operands::Big(a, b) => match a.div_rem(&b) {
+							Some((_, remainder)) => self.big_result(remainder),
+							None => return Err(self.division_by_zero().into()),
+						},
+					};
+					self.stack.push(result);
 				}
 
-				Some(OpCode::OpMultiply) => {
-					let b = self.pop()?;
-					let a = self.pop()?;
+				Some(OpCode::OpIntDivide) => {
+					let result = match self.numeric_operands()? {
+						Operands::Int(_, 0) => return Err(self.division_by_zero().into()),
+						Operands::Int(a, b) => self.int_result(a.checked_div(b).map(|quotient| {
+							// Round towards negative infinity, not towards zero.
+							if a % b != 0 && (a < 0) != (b < 0) { quotient - 1 } else { quotient }
+						}))?,
+						Operands::Float(a, b) => Value::number((a / b).floor()),
+						Operands::Big(a, b) => match a.div_floor(&b) {
+							Some(quotient) => self.big_result(quotient),
+							None => return Err(self.division_by_zero().into()),
+						},
+					};
+					self.stack.push(result);
+				}
 
-					self.stack.push(match (a, b) {
-						(Value::Number(a), Value::Number(b)) => {
-							Ok(Value::Number(a * b))
+				Some(OpCode::OpPower) => {
+					let result = match self.numeric_operands()? {
+						Operands::Int(a, b) if b >= 0 => {
+							// A base of -1, 0 or 1 never overflows, however large the
+							// exponent: only its parity matters.
+							let b = if (-1..=1).contains(&a) && b > 1 { 2 - b % 2 } else { b };
+							self.int_result(u32::try_from(b).ok().and_then(|b| a.checked_pow(b)))?
 						}
-						_ => Err(RuntimeError::new(
-							self.instruction_line,
-							"Operands must be two numbers.",
-						)),
-					}?);
+						Operands::Int(a, b) => Value::number((a as f64).powf(b as f64)),
+						Operands::Float(a, b) => Value::number(a.powf(b)),
+						Operands::Big(a, b) if b < BigInt::zero() => Value::number(a.to_f64().powf(b.to_f64())),
+						Operands::Big(a, b) => {
+							// Powers of -1, 0 and 1 stay small; any other base grows by
+							// its own size with each factor.
+							let result = match a.to_i64() {
+								_ if b.is_zero() => BigInt::from(1),
+								Some(0 | 1) => a,
+								Some(-1) if b.is_odd() => a,
+								Some(-1) => BigInt::from(1),
+								_ => {
+									let exponent = b.to_i64()
+										.and_then(|b| u32::try_from(b).ok())
+										.filter(|&b| a.bits().saturating_mul(b as u64) <= BIGINT_POWER_BITS_MAX)
+										.ok_or(RuntimeError::new(self.instruction_line, "Exponent too large."))?;
+									a.pow(exponent)
+								}
+							};
+							self.big_result(result)
+						}
+					};
+					self.stack.push(result);
 				}
 
-				Some(OpCode::OpGreater) => {
-					let b = self.pop()?;
-					let a = self.pop()?;
+				Some(OpCode::OpBitAnd) => {
+					let (a, b, float) = self.integer_operands()?;
+					let result = self.bitwise_result(Some(a & b), float)?;
+					self.stack.push(result);
+				}
 
-					self.stack.push(match (a, b) {
-						(Value::Number(a), Value::Number(b)) => {
-							Ok(Value::Bool(a > b))
-						}
-						_ => Err(RuntimeError::new(
-							self.instruction_line,
-							"Cannot compare two non numbers",
-						)),
-					}?);
+				Some(OpCode::OpBitOr) => {
+					let (a, b, float) = self.integer_operands()?;
+					let result = self.bitwise_result(Some(a | b), float)?;
+					self.stack.push(result);
 				}
 
-				Some(OpCode::OpLess) => {
-					let b = self.pop()?;
-					let a = self.pop()?;
+				Some(OpCode::OpBitXor) => {
+					let (a, b, float) = self.integer_operands()?;
+					let result = self.bitwise_result(Some(a ^ b), float)?;
+					self.stack.push(result);
+				}
 
-					self.stack.push(match (a, b) {
-						(Value::Number(a), Value::Number(b)) => {
-							Ok(Value::Bool(a < b))
-						}
-						_ => Err(RuntimeError::new(
-							self.instruction_line,
-							"Cannot compare two non numbers",
-						)),
-					}?);
+				Some(OpCode::OpBitNot) => {
+					let value = self.pop()?;
+					let a = self.integer(value)?;
+					let result = self.bitwise_result(Some(!a), value.as_number().is_some())?;
+					self.stack.push(result);
 				}
 
-				Some(OpCode::OpConstant) => {
-					let constant = self.read_constant()?;
+				Some(OpCode::OpShiftLeft) => {
+					let (a, b, float) = self.integer_operands()?;
+					let shift = self.shift_amount(b)?;
+					// Overflows if shifting back doesn't restore `a`: a bit, or the
+					// sign, was shifted out.
+					let shifted = a << shift;
+					let result = self.bitwise_result(Some(shifted).filter(|n| n >> shift == a), float)?;
+					self.stack.push(result);
+				}
+
+				Some(OpCode::OpShiftRight) => {
+					let (a, b, float) = self.integer_operands()?;
+					let shift = self.shift_amount(b)?;
+					// An arithmetic shift: negative numbers stay negative.
+					let result = self.bitwise_result(Some(a >> shift), float)?;
+					self.stack.push(result);
+				}
+
+				Some(OpCode::OpMultiply) => {
+					let result = match self.numeric_operands()? {
+						Operands::Int(a, b) => self.int_result(a.checked_mul(b))?,
+						Operands::Float(a, b) => Value::number(a * b),
+						Operands::Big(a, b) => self.big_result(&a * &b),
+					};
+					self.stack.push(result);
+				}
+
+				Some(OpCode::OpGreater) => {
+					let ordering = self.compare()?;
+					self.stack.push(Value::bool_val(ordering == Some(Ordering::Greater)));
+				}
+
+				Some(OpCode::OpLess) => {
+					let ordering = self.compare()?;
+					self.stack.push(Value::bool_val(ordering == Some(Ordering::Less)));
+				}
+
+				Some(op @ (OpCode::OpConstant | OpCode::OpConstantLong)) => {
+					let constant = self.read_constant(op.is_long())?;
 					self.stack.push(constant);
 				}
 
 				Some(OpCode::OpNil) => {
-					self.stack.push(Value::Nil);
+					self.stack.push(Value::nil());
 				}
 
 				Some(OpCode::OpTrue) => {
-					self.stack.push(Value::Bool(true));
+					self.stack.push(Value::bool_val(true));
 				}
 
 				Some(OpCode::OpFalse) => {
-					self.stack.push(Value::Bool(false));
+					self.stack.push(Value::bool_val(false));
 				}
 
 				Some(OpCode::OpPop) => {
 					self.pop()?;
 				}
 
-				Some(OpCode::OpDefineGlobal) => {
-					let name_value = self.read_constant()?;
-					if let Value::Obj(_) = name_value {
-						let name = name_value.to_string();
-						self.globals.insert(name, self.peek()?.clone());
-						self.pop()?;
-					} else {
-						return Err(RLoxError::RuntimeError(RuntimeError::new(
-							self.instruction_line,
-							"Variable name must be a string.",
-						)));
-					}
+				Some(OpCode::OpDup) => {
+					let value = *self.peek(0)?;
+					self.stack.push(value);
 				}
 
-				Some(OpCode::OpSetGlobal) => {
-					let name_value = self.read_constant()?;
-					if let Value::Obj(_) = name_value {
-						let name = name_value.to_string();
-						self.globals.insert(name, self.peek()?.clone());
-					} else {
-						return Err(RLoxError::RuntimeError(RuntimeError::new(
-							self.instruction_line,
-							"Variable name must be a string.",
-						)));
+				Some(OpCode::OpSwap) => {
+					let b = self.pop()?;
+					let a = self.pop()?;
+					self.stack.push(b);
+					self.stack.push(a);
+				}
+
+				Some(OpCode::OpOver) => {
+					let value = *self.peek(1)?;
+					self.stack.push(value);
+				}
+
+				Some(OpCode::OpGetLocal) => {
+					let slot = self.frame()?.slots + self.read_byte()? as usize;
+					let value = self.stack.get(slot).copied()
+						.ok_or(RuntimeError::new(self.instruction_line, "Invalid local slot"))?;
+					self.stack.push(value);
+				}
+
+				Some(OpCode::OpSetLocal) => {
+					let slot = self.frame()?.slots + self.read_byte()? as usize;
+					let value = *self.peek(0)?;
+					let local = self.stack.get_mut(slot)
+						.ok_or(RuntimeError::new(self.instruction_line, "Invalid local slot"))?;
+					*local = value;
+				}
+
+				Some(OpCode::OpGetUpvalue) => {
+					let slot = self.read_byte()? as usize;
+					let upvalue = self.upvalue(slot)?;
+					let value = match self.heap.get(upvalue) {
+						Obj::Upvalue(Upvalue::Open(index)) => self.stack.get(*index).copied()
+							.ok_or(RuntimeError::new(self.instruction_line, "Invalid upvalue slot"))?,
+						Obj::Upvalue(Upvalue::Closed(value)) => *value,
+						_ => return Err(RuntimeError::new(self.instruction_line, "Invalid upvalue").into()),
+					};
+					self.stack.push(value);
+				}
+
+				Some(OpCode::OpSetUpvalue) => {
+					let slot = self.read_byte()? as usize;
+					let upvalue = self.upvalue(slot)?;
+					let value = *self.peek(0)?;
+					match self.heap.get_mut(upvalue) {
+						Obj::Upvalue(Upvalue::Open(index)) => {
+							let local = self.stack.get_mut(*index)
+								.ok_or(RuntimeError::new(self.instruction_line, "Invalid upvalue slot"))?;
+							*local = value;
+						}
+						Obj::Upvalue(Upvalue::Closed(closed)) => *closed = value,
+						_ => return Err(RuntimeError::new(self.instruction_line, "Invalid upvalue").into()),
 					}
 				}
 
-				Some(OpCode::OpGetGlobal) => {
-					let name_value = self.read_constant()?;
-					if let Value::Obj(_) = name_value {
-						let name = name_value.to_string();
-						if let Some(val) = self.globals.get(&name) {
-							self.stack.push(val.clone());
+				Some(op @ (OpCode::OpClosure | OpCode::OpClosureLong)) => {
+					let function = match self.read_constant(op.is_long())?.as_obj() {
+						Some(obj) if self.heap.get(obj).as_function().is_some() => obj,
+						_ => return Err(RuntimeError::new(self.instruction_line, "Closure constant must be a function").into()),
+					};
+
+					let upvalue_count = self.function(function).upvalue_count;
+					let mut upvalues = Vec::with_capacity(upvalue_count);
+					for _ in 0..upvalue_count {
+						let is_local = self.read_byte()? == 1;
+						let index = self.read_byte()? as usize;
+
+						if is_local {
+							let slot = self.frame()?.slots + index;
+							upvalues.push(self.capture_upvalue(slot));
 						} else {
-							return Err(RLoxError::RuntimeError(RuntimeError::new(
-								self.instruction_line,
-								&format!("Undefined variable '{}'.", name).to_owned()
-							)));
+							upvalues.push(self.upvalue(index)?);
 						}
-					} else {
-						return Err(RLoxError::RuntimeError(RuntimeError::new(
-							self.instruction_line,
-							"Variable name must be a string.",
-						)));
 					}
+
+					let closure = self.alloc(Obj::Closure(Closure { function, upvalues }));
+					self.stack.push(Value::obj(closure));
+				}
+
+				Some(OpCode::OpCloseUpvalue) => {
+					self.close_upvalues(self.stack.len() - 1);
+					self.pop()?;
+				}
+
+				Some(op @ (OpCode::OpClass | OpCode::OpClassLong)) => {
+					let name = self.read_string(op.is_long())?;
+					let class = self.alloc(Obj::Class(Class::new(name)));
+					self.stack.push(Value::obj(class));
+				}
+
+				Some(OpCode::OpInherit) => {
+					let methods = self.peek(1)?.as_obj()
+						.and_then(|superclass| self.heap.get(superclass).as_class())
+						.map(|class| class.methods.clone())
+						.ok_or(RuntimeError::new(self.instruction_line, "Superclass must be a class."))?;
+
+					if let Some(subclass) = self.peek(0)?.as_obj()
+						&& let Obj::Class(subclass) = self.heap.get_mut(subclass) {
+						subclass.methods.extend(methods);
+					}
+
+					self.pop()?;
+				}
+
+				Some(op @ (OpCode::OpGetSuper | OpCode::OpGetSuperLong)) => {
+					let name = self.read_string(op.is_long())?;
+					let superclass = self.pop_class()?;
+					self.bind_method(superclass, name)?;
+				}
+
+				Some(op @ (OpCode::OpSuperInvoke | OpCode::OpSuperInvokeLong)) => {
+					let method = self.read_string(op.is_long())?;
+					let arg_count = self.read_byte()?;
+					let superclass = self.pop_class()?;
+					self.invoke_from_class(superclass, method, arg_count)?;
+				}
+
+				Some(op @ (OpCode::OpMethod | OpCode::OpMethodLong)) => {
+					let name = self.read_string(op.is_long())?;
+					self.define_method(name)?;
+				}
+
+				Some(op @ (OpCode::OpInvoke | OpCode::OpInvokeLong)) => {
+					let method = self.read_string(op.is_long())?;
+					let arg_count = self.read_byte()?;
+					self.invoke(method, arg_count)?;
+				}
+
+				Some(op @ (OpCode::OpGetProperty | OpCode::OpGetPropertyLong)) => {
+					let instance = match self.peek(0)?.as_obj() {
+						Some(obj) if self.heap.get(obj).as_instance().is_some() => obj,
+						_ => return Err(RuntimeError::new(self.instruction_line, "Only instances have properties.").into()),
+					};
+					let name = self.read_string(op.is_long())?;
+
+					let field = self.heap.get(instance).as_instance()
+						.and_then(|instance| instance.fields.get(&name))
+						.copied();
+
+					match field {
+						Some(value) => {
+							self.pop()?;
+							self.stack.push(value);
+						}
+						None => {
+							let class = self.instance_class(instance);
+							self.bind_method(class, name)?;
+						}
+					}
+				}
+
+				Some(op @ (OpCode::OpSetProperty | OpCode::OpSetPropertyLong)) => {
+					let instance = match self.peek(1)?.as_obj() {
+						Some(obj) if self.heap.get(obj).as_instance().is_some() => obj,
+						_ => return Err(RuntimeError::new(self.instruction_line, "Only instances have fields.").into()),
+					};
+					let name = self.read_string(op.is_long())?;
+					let value = *self.peek(0)?;
+
+					if let Obj::Instance(instance) = self.heap.get_mut(instance) {
+						instance.fields.insert(name, value);
+					}
+
+					self.pop()?;
+					self.pop()?;
+					self.stack.push(value);
+				}
+
+				Some(OpCode::OpDefineGlobal) => {
+					let slot = self.read_byte()? as u32;
+					self.define_global(slot)?;
+				}
+
+				Some(OpCode::OpDefineGlobalLong) => {
+					let slot = self.read_long()?;
+					self.define_global(slot)?;
+				}
+
+				Some(OpCode::OpSetGlobal) => {
+					let slot = self.read_byte()? as u32;
+					*self.global(slot)? = *self.peek(0)?;
+				}
+
+				Some(OpCode::OpSetGlobalLong) => {
+					let slot = self.read_long()?;
+					*self.global(slot)? = *self.peek(0)?;
+				}
+
+				Some(OpCode::OpGetGlobal) => {
+					let slot = self.read_byte()? as u32;
+					self.get_global(slot)?;
+				}
+
+				Some(OpCode::OpGetGlobalLong) => {
+					let slot = self.read_long()?;
+					self.get_global(slot)?;
+				}
+
+				Some(OpCode::OpJump) => {
+					let offset = self.read_short()?;
+					self.frame_mut()?.ip += offset as usize;
+				}
+
+				Some(OpCode::OpJumpIfFalse) => {
+					let offset = self.read_short()?;
+					if self.is_falsey(self.peek(0)?) {
+						self.frame_mut()?.ip += offset as usize;
+					}
+				}
+
+				Some(OpCode::OpJumpTable) => {
+					let min = self.read_short()? as i16;
+					let len = self.read_short()? as usize;
+					let value = self.pop()?;
+
+					// Values outside the table, and non-integers, take the
+					// default entry stored after the others.
+					let index = Number::of(value, &self.heap)
+						.map(|n| n.to_f64())
+						.filter(|n| n.fract() == 0.0)
+						.map(|n| n - min as f64)
+						.filter(|index| *index >= 0.0 && *index < len as f64)
+						.map_or(len, |index| index as usize);
+
+					let table_start = self.frame()?.ip;
+					self.frame_mut()?.ip = table_start + index * 2;
+					let offset = self.read_short()? as usize;
+					self.frame_mut()?.ip = table_start + (len + 1) * 2 + offset;
+				}
+
+				Some(OpCode::OpLoop) => {
+					let offset = self.read_short()?;
+					self.frame_mut()?.ip -= offset as usize;
 				}
 
 				Some(OpCode::OpNot) => {
 					let val = self.pop()?;
-					let not = Value::Bool(self.is_falsey(&val));
+					let not = Value::bool_val(self.is_falsey(&val));
 					self.stack.push(not);
 				}
 
 				Some(OpCode::OpEqual) => {
 					let b = self.pop()?;
 					let a = self.pop()?;
-					let equals = Value::Bool(self.values_equal(&a, &b));
-					self.stack.push(equals);
+					// Strings are interned, so objects compare by identity. Numbers
+					// compare by value, across integers, floats and bigints.
+					let equal = match (Number::of(a, &self.heap), Number::of(b, &self.heap)) {
+						(Some(a), Some(b)) => a.compare(&b) == Some(Ordering::Equal),
+						_ => a == b,
+					};
+					self.stack.push(Value::bool_val(equal));
 				}
 
 				_ => {}
@@ -257,36 +748,400 @@ impl VM {
 		}
 	}
 
+	fn call_value(&mut self, callee: Value, arg_count: u8) -> Result<(), RLoxError> {
+		match callee.as_obj() {
+			Some(obj) => match self.heap.get(obj) {
+				Obj::Closure(_) => self.call(obj, arg_count),
+				Obj::BoundMethod(bound) => {
+					let (receiver, method) = (bound.receiver, bound.method);
+					let slot = self.stack.len() - arg_count as usize - 1;
+					self.stack[slot] = receiver;
+					self.call(method, arg_count)
+				}
+				Obj::Class(class) => {
+					let initializer = class.methods.get(&self.init_string).copied();
+
+					let instance = self.alloc(Obj::Instance(Instance::new(obj)));
+					let slot = self.stack.len() - arg_count as usize - 1;
+					self.stack[slot] = Value::obj(instance);
+
+					match initializer {
+						Some(initializer) => self.call(initializer, arg_count),
+						None if arg_count != 0 => Err(RuntimeError::new(
+							self.instruction_line,
+							&format!("Expected 0 arguments but got {}.", arg_count),
+						).into()),
+						None => Ok(()),
+					}
+				}
+				Obj::Native(native) => {
+					let (arity, function) = (native.arity, Rc::clone(&native.function));
+					self.call_native(arity, function, arg_count)
+				}
+				_ => Err(RuntimeError::new(self.instruction_line, "Can only call functions and classes.").into()),
+			},
+			_ => Err(RuntimeError::new(self.instruction_line, "Can only call functions and classes.").into()),
+		}
+	}
+
+	/// Calls method `name` on the receiver sitting below the arguments
+	/// without creating a bound method for it.
+	fn invoke(&mut self, name: GcRef, arg_count: u8) -> Result<(), RLoxError> {
+		let receiver = *self.peek(arg_count as usize)?;
+		let instance = receiver.as_obj()
+			.and_then(|obj| self.heap.get(obj).as_instance())
+			.ok_or(RuntimeError::new(self.instruction_line, "Only instances have methods."))?;
+
+		// A field holding a callable shadows a method of the same name.
+		if let Some(field) = instance.fields.get(&name).copied() {
+			let slot = self.stack.len() - arg_count as usize - 1;
+			self.stack[slot] = field;
+			return self.call_value(field, arg_count);
+		}
+
+		let class = instance.class;
+		self.invoke_from_class(class, name, arg_count)
+	}
+
+	fn invoke_from_class(&mut self, class: GcRef, name: GcRef, arg_count: u8) -> Result<(), RLoxError> {
+		let method = self.find_method(class, name)?;
+		self.call(method, arg_count)
+	}
+
+	fn find_method(&self, class: GcRef, name: GcRef) -> Result<GcRef, RuntimeError> {
+		self.heap.get(class).as_class()
+			.and_then(|class| class.methods.get(&name))
+			.copied()
+			.ok_or_else(|| RuntimeError::new(
+				self.instruction_line,
+				&format!("Undefined property '{}'.", Value::obj(name).display(&self.heap)),
+			))
+	}
+
+	/// Replaces the instance on top of the stack with its method `name`
+	/// bound to it.
+	fn bind_method(&mut self, class: GcRef, name: GcRef) -> Result<(), RLoxError> {
+		let method = self.find_method(class, name)?;
+		let receiver = *self.peek(0)?;
+		let bound = self.alloc(Obj::BoundMethod(BoundMethod { receiver, method }));
+
+		self.pop()?;
+		self.stack.push(Value::obj(bound));
+		Ok(())
+	}
+
+	fn define_method(&mut self, name: GcRef) -> Result<(), RLoxError> {
+		let method = match self.peek(0)?.as_obj() {
+			Some(method) => method,
+			None => return Err(RuntimeError::new(self.instruction_line, "Method must be a closure.").into()),
+		};
+
+		match self.peek(1)?.as_obj() {
+			Some(class) => match self.heap.get_mut(class) {
+				Obj::Class(class) => class.methods.insert(name, method),
+				_ => return Err(RuntimeError::new(self.instruction_line, "Methods can only be defined on classes.").into()),
+			},
+			_ => return Err(RuntimeError::new(self.instruction_line, "Methods can only be defined on classes.").into()),
+		};
+
+		self.pop()?;
+		Ok(())
+	}
+
+	fn pop_class(&mut self) -> Result<GcRef, RLoxError> {
+		match self.pop()?.as_obj() {
+			Some(class) if self.heap.get(class).as_class().is_some() => Ok(class),
+			_ => Err(RuntimeError::new(self.instruction_line, "Superclass must be a class.").into()),
+		}
+	}
+
+	fn instance_class(&self, instance: GcRef) -> GcRef {
+		self.heap.get(instance).as_instance().expect("Reference must point at an instance").class
+	}
+
+	fn call_native(&mut self, arity: usize, function: Rc<NativeFn>, arg_count: u8) -> Result<(), RLoxError> {
+		if arg_count as usize != arity {
+			return Err(RuntimeError::new(
+				self.instruction_line,
+				&format!("Expected {} arguments but got {}.", arity, arg_count),
+			).into());
+		}
+
+		let args_start = self.stack.len() - arg_count as usize;
+		let result = function(&mut self.heap, &self.stack[args_start..])
+			.map_err(|e| RuntimeError::new(self.instruction_line, &e.message))?;
+
+		self.stack.truncate(args_start - 1);
+		self.stack.push(result);
+		Ok(())
+	}
+
+	fn call(&mut self, closure: GcRef, arg_count: u8) -> Result<(), RLoxError> {
+		let function = match self.heap.get(closure).as_closure() {
+			Some(c) => c.function,
+			None => return Err(RuntimeError::new(self.instruction_line, "Can only call functions and classes.").into()),
+		};
+		let (arity, code) = {
+			let function = self.function(function);
+			(function.arity, Rc::clone(&function.chunk.code))
+		};
+
+		if arg_count as usize != arity {
+			return Err(RuntimeError::new(
+				self.instruction_line,
+				&format!("Expected {} arguments but got {}.", arity, arg_count),
+			).into());
+		}
+
+		if self.frames.len() == FRAMES_MAX {
+			return Err(RuntimeError::new(self.instruction_line, "Stack overflow.").into());
+		}
+
+		self.frames.push(CallFrame {
+			closure,
+			function,
+			code,
+			ip: 0,
+			slots: self.stack.len() - arg_count as usize - 1,
+		});
+
+		Ok(())
+	}
+
+	/// The function behind a `GcRef` the compiler or VM produced as one.
+	fn function(&self, function: GcRef) -> &Function {
+		self.heap.get(function).as_function().expect("Reference must point at a function")
+	}
+
+	fn global(&mut self, slot: u32) -> Result<&mut Value, RuntimeError> {
+		self.globals.get_mut(slot as usize)
+			.ok_or(RuntimeError::new(self.instruction_line, "Invalid global slot"))
+	}
+
+	fn define_global(&mut self, slot: u32) -> Result<(), RLoxError> {
+		*self.global(slot)? = *self.peek(0)?;
+		self.pop()?;
+		Ok(())
+	}
+
+	fn get_global(&mut self, slot: u32) -> Result<(), RLoxError> {
+		let value = *self.global(slot)?;
+		if value.is_undefined() {
+			let name = self.global_names.name(slot).map_or(Value::undefined(), Value::obj);
+			return Err(RLoxError::RuntimeError(RuntimeError::new(
+				self.instruction_line,
+				&format!("Undefined variable '{}'.", name.display(&self.heap)).to_owned()
+			)));
+		}
+
+		self.stack.push(value);
+		Ok(())
+	}
+
+	fn upvalue(&self, slot: usize) -> Result<GcRef, RuntimeError> {
+		self.heap.get(self.frame()?.closure)
+			.as_closure()
+			.and_then(|closure| closure.upvalues.get(slot))
+			.copied()
+			.ok_or(RuntimeError::new(self.instruction_line, "Invalid upvalue index"))
+	}
+
+	/// Returns the open upvalue for `slot`, creating one if no closure has
+	/// captured that stack slot yet.
+	fn capture_upvalue(&mut self, slot: usize) -> GcRef {
+		let existing = self.open_upvalues.iter()
+			.find(|upvalue| self.heap.get(**upvalue).as_upvalue() == Some(&Upvalue::Open(slot)));
+		if let Some(upvalue) = existing {
+			return *upvalue;
+		}
+
+		let upvalue = self.alloc(Obj::Upvalue(Upvalue::Open(slot)));
+		self.open_upvalues.push(upvalue);
+		upvalue
+	}
+
+	/// Moves every captured variable at or above `last` off the stack and
+	/// into its upvalue.
+	fn close_upvalues(&mut self, last: usize) {
+		let (stack, heap) = (&self.stack, &mut self.heap);
+		self.open_upvalues.retain(|upvalue| {
+			let upvalue = heap.get_mut(*upvalue);
+			match *upvalue {
+				Obj::Upvalue(Upvalue::Open(slot)) if slot >= last => {
+					*upvalue = Obj::Upvalue(Upvalue::Closed(stack[slot]));
+					false
+				}
+				_ => true,
+			}
+		});
+	}
+
+	fn concatenate(&mut self, a: GcRef, b: GcRef) -> Result<Value, RuntimeError> {
+		let joined = match (self.heap.get(a).as_string(), self.heap.get(b).as_string()) {
+			(Some(a), Some(b)) => [a, b].concat(),
+			_ => return Err(RuntimeError::new(
+				self.instruction_line,
+				"Operands must be two numbers or two strings.",
+			)),
+		};
+
+		Ok(Value::obj(self.intern(&joined)))
+	}
+
+	/// Pops the two operands of an arithmetic instruction, `b` first.
+	fn numeric_operands(&mut self) -> Result<Operands, RuntimeError> {
+		let b = self.pop()?;
+		let a = self.pop()?;
+		Operands::of(a, b, &self.heap).ok_or(RuntimeError::new(self.instruction_line, "Operands must be two numbers."))
+	}
+
+	/// Wraps the result of an integer operation, which is `None` if it
+	/// overflowed.
+	fn int_result(&mut self, result: Option<i64>) -> Result<Value, RuntimeError> {
+		let n = result.ok_or(RuntimeError::new(self.instruction_line, "Integer overflow."))?;
+		Ok(self.int(n))
+	}
+
+	/// An integer value, boxed on the heap if the representation can't hold
+	/// it inline.
+	fn int(&mut self, n: i64) -> Value {
+		Value::checked_int(n).unwrap_or_else(|| Value::obj(self.alloc(Obj::Int(n))))
+	}
+
+	/// Moves the result of a bigint operation onto the heap.
+	fn big_result(&mut self, n: BigInt) -> Value {
+		Value::obj(self.alloc(Obj::BigInt(n)))
+	}
+
+	fn division_by_zero(&self) -> RuntimeError {
+		RuntimeError::new(self.instruction_line, "Integer division by zero.")
+	}
+
+	/// Pops and orders the two operands of a comparison. NaN is unordered.
+	fn compare(&mut self) -> Result<Option<Ordering>, RuntimeError> {
+		let b = self.pop()?;
+		let a = self.pop()?;
+		match (Number::of(a, &self.heap), Number::of(b, &self.heap)) {
+			(Some(a), Some(b)) => Ok(a.compare(&b)),
+			_ => Err(RuntimeError::new(self.instruction_line, "Cannot compare two non numbers")),
+		}
+	}
+
+	/// Pops the two operands of a bitwise operator, `b` first, and tells
+	/// whether either was a float.
+	fn integer_operands(&mut self) -> Result<(i64, i64, bool), RuntimeError> {
+		let b = self.pop()?;
+		let a = self.pop()?;
+		let float = a.as_number().is_some() || b.as_number().is_some();
+		Ok((self.integer(a)?, self.integer(b)?, float))
+	}
+
+	/// Wraps the result of a bitwise operator, which is `None` if it
+	/// overflowed. Like arithmetic, a float operand makes the result a float;
+	/// it must then stay within 2^53 so that it is exact, as the operands are.
+	fn bitwise_result(&mut self, result: Option<i64>, float: bool) -> Result<Value, RuntimeError> {
+		if !float {
+			return self.int_result(result);
+		}
+
+		match result {
+			Some(n) if n.unsigned_abs() <= INTEGER_MAX as u64 => Ok(Value::number(n as f64)),
+			_ => Err(RuntimeError::new(self.instruction_line, "Result must not exceed 2^53 in magnitude.")),
+		}
+	}
+
+	/// Bitwise operators work on integers, and on floats holding exact
+	/// integers. Past 2^53 a double can't represent every integer, so larger
+	/// floats are rejected instead of silently operating on a rounded value.
+	fn integer(&self, value: Value) -> Result<i64, RuntimeError> {
+		let n = match Number::of(value, &self.heap) {
+			Some(Number::Int(n)) => return Ok(n),
+			Some(Number::Float(n)) if n.fract() == 0.0 => n,
+			_ => return Err(RuntimeError::new(self.instruction_line, "Operands must be integers.")),
+		};
+
+		if n.abs() > INTEGER_MAX {
+			return Err(RuntimeError::new(self.instruction_line, "Operands must not exceed 2^53 in magnitude."));
+		}
+		Ok(n as i64)
+	}
+
+	fn shift_amount(&self, shift: i64) -> Result<u32, RuntimeError> {
+		match u32::try_from(shift) {
+			Ok(shift) if shift < i64::BITS => Ok(shift),
+			_ => Err(RuntimeError::new(self.instruction_line, "Shift amount must be between 0 and 63.")),
+		}
+	}
+
+	fn frame(&self) -> Result<&CallFrame, RuntimeError> {
+		self.frames
+			.last()
+			.ok_or(RuntimeError::new(self.instruction_line, "No active call frame"))
+	}
+
+	fn frame_mut(&mut self) -> Result<&mut CallFrame, RuntimeError> {
+		self.frames
+			.last_mut()
+			.ok_or(RuntimeError::new(self.instruction_line, "No active call frame"))
+	}
+
 	fn read_byte(&mut self) -> Result<u8, RLoxError> {
-		let byte = self
+		let frame = self.frame()?;
+		let byte = frame
 			.code
 			.borrow()
-			.get(self.ip)
+			.get(frame.ip)
 			.copied()
 			.ok_or_else(|| {
 				let line = self.current_line().unwrap_or(0);
 				RuntimeError::new(line, "End of Stream")
 			})?;
-		self.ip += 1;
+		self.frame_mut()?.ip += 1;
 		Ok(byte)
 	}
 
-	fn read_constant(&mut self) -> Result<Value, RLoxError> {
-		let position = self.read_byte()?;
-		let chunk = self.chunk.borrow();
-		let constant = chunk
+	fn read_short(&mut self) -> Result<u16, RLoxError> {
+		let hi = self.read_byte()? as u16;
+		let lo = self.read_byte()? as u16;
+		Ok(hi << 8 | lo)
+	}
+
+	/// Reads a three-byte, big-endian operand.
+	fn read_long(&mut self) -> Result<u32, RLoxError> {
+		let hi = self.read_byte()? as u32;
+		let mid = self.read_byte()? as u32;
+		let lo = self.read_byte()? as u32;
+		Ok(hi << 16 | mid << 8 | lo)
+	}
+
+	/// Reads a constant operand, three bytes wide if `long`.
+	fn read_constant(&mut self, long: bool) -> Result<Value, RLoxError> {
+		let position = if long { self.read_long()? } else { self.read_byte()? as u32 };
+		self.constant(position as usize)
+	}
+
+	fn constant(&self, position: usize) -> Result<Value, RLoxError> {
+		let constant = self.function(self.frame()?.function)
+			.chunk
 			.constants
-			.get(position as usize)
+			.get(position)
 			.ok_or_else(|| {
 				let line = self.current_line().unwrap_or(0);
 				RuntimeError::new(line, "Failed to get constant")
 			})?;
-		Ok((**constant).clone())
+		Ok(*constant)
+	}
+
+	fn read_string(&mut self, long: bool) -> Result<GcRef, RLoxError> {
+		match self.read_constant(long)?.as_obj() {
+			Some(name) if self.heap.get(name).as_string().is_some() => Ok(name),
+			_ => Err(RuntimeError::new(self.instruction_line, "Variable name must be a string.").into()),
+		}
 	}
 
 	fn current_line(&self) -> Option<usize> {
-		let chunk = self.chunk.borrow();
-		chunk.lines.get(self.ip).copied()
+		let frame = self.frames.last()?;
+		self.function(frame.function).chunk.line_at(frame.ip)
 	}
 
 	fn pop(&mut self) -> Result<Value, RuntimeError> {
@@ -295,20 +1150,12 @@ impl VM {
 			.ok_or(RuntimeError::new(self.instruction_line, "No value on stack"))
 	}
 
-	fn peek(&self) -> Result<&Value, RLoxError> {
-		self.stack.iter().peekable().next()
+	fn peek(&self, distance: usize) -> Result<&Value, RLoxError> {
+		self.stack.iter().rev().nth(distance)
 			.ok_or(RLoxError::RuntimeError(RuntimeError::new(self.instruction_line, "No value to peek")))
 	}
 
 	fn is_falsey(&self, value: &Value) -> bool {
-		match value {
-			Value::Bool(val) => !val,
-			Value::Nil => true,
-			_ => false,
-		}
-	}
-
-	fn values_equal(&self, a: &Value, b: &Value) -> bool {
-		a == b
+		value.is_nil() || value.as_bool() == Some(false)
 	}
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+mod common;
+
+use common::run_ok;
+
+#[test]
+fn modulo_takes_the_sign_of_the_dividend() {
+	let source = r#"
+		print 7 % 3;
+		print -7 % 3;
+		print 7 % -3;
+		print 5.5 % 2;
+	"#;
+	assert_eq!(run_ok(source), ["1", "-1", "1", "1.5"]);
+}
+
+#[test]
+fn integer_division_rounds_down() {
+	let source = r#"
+		print 7 ~/ 2;
+		print -7 ~/ 2;
+		print 7 ~/ -2;
+		print 6 ~/ 3;
+	"#;
+	assert_eq!(run_ok(source), ["3", "-4", "-4", "2"]);
+}
+
+#[test]
+fn power_is_right_associative_and_binds_tighter_than_unary() {
+	let source = r#"
+		print 2 ** 3 ** 2;
+		print -2 ** 2;
+		print 2 * 3 ** 2;
+		print 2 ** -1;
+	"#;
+	assert_eq!(run_ok(source), ["512", "-4", "18", "0.5"]);
+}
+
+#[test]
+fn huge_exponents_of_small_bases() {
+	let source = r#"
+		print 1 ** 5000000000;
+		print 0 ** 5000000000;
+		print (-1) ** 5000000000;
+		print (-1) ** 5000000001;
+	"#;
+	assert_eq!(run_ok(source), ["1", "0", "1", "-1"]);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::{run, run_ok};
+
+#[test]
+fn compound_assignment_on_variables() {
+	let source = r#"
+		var g = 10;
+		g += 5; print g;
+		g -= 3; print g;
+		g *= 2; print g;
+		g /= 4; print g;
+		g %= 4; print g;
+
+		{
+			var local = "a";
+			local += "b";
+			print local;
+		}
+	"#;
+	assert_eq!(run_ok(source), ["15", "12", "24", "6", "2", "ab"]);
+}
+
+#[test]
+fn compound_assignment_on_properties() {
+	let source = r#"
+		class Box {}
+		var a = Box();
+		a.b = 1;
+		a.b += 1;
+		print a.b;
+		print a.b *= 10;
+
+		var calls = 0;
+		fun get() { calls += 1; return a; }
+		get().b -= 5;
+		print a.b;
+		print calls;
+	"#;
+	assert_eq!(run_ok(source), ["2", "20", "15", "1"]);
+}
+
+#[test]
+fn increments_on_locals_upvalues_and_globals() {
+	let source = r#"
+		var g = 0;
+		print g++;
+		print ++g;
+		print g;
+
+		{
+			var local = 5;
+			print local--;
+			print --local;
+			print local;
+		}
+
+		fun counter() {
+			var count = 0;
+			fun next() { count++; return count; }
+			return next;
+		}
+		var next = counter();
+		next();
+		print next();
+	"#;
+	assert_eq!(run_ok(source), ["0", "2", "2", "5", "3", "3", "2"]);
+}
+
+#[test]
+fn increments_on_properties() {
+	let source = r#"
+		class Box {}
+		var a = Box();
+		a.b = 1;
+		print a.b++;
+		print ++a.b;
+		print a.b--;
+		print a.b;
+	"#;
+	assert_eq!(run_ok(source), ["1", "3", "3", "2"]);
+}
+
+#[test]
+fn increment_needs_a_target() {
+	let output = run("++1;");
+	assert_eq!(output.stdout, "");
+	assert!(output.stderr.contains("Invalid increment target."));
+
+	let output = run("var a = 1; (a)++;");
+	assert_eq!(output.stdout, "");
+	assert!(output.stderr.contains("Expect ';' after expression."));
+}
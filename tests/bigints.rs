@@ -0,0 +1,47 @@
+mod common;
+
+use common::{run_err, run_ok};
+
+#[test]
+fn bigint_arithmetic() {
+	let source = r#"
+		print 9223372036854775807n + 1;
+		print 2n ** 100;
+		print -7n % 2n;
+		print -7n ~/ 2n;
+		print type(1n);
+		print 10n == 10;
+	"#;
+	assert_eq!(run_ok(source), [
+		"9223372036854775808",
+		"1267650600228229401496703205376",
+		"-1",
+		"-4",
+		"bigint",
+		"true",
+	]);
+}
+
+#[test]
+fn huge_powers_of_small_bigints() {
+	let source = r#"
+		print 0n ** 0n;
+		print 0n ** 4000000000n;
+		print 1n ** 4000000000n;
+		print 1n ** 18446744073709551616n;
+		print (-1n) ** 4000000000n;
+		print (-1n) ** 4000000001n;
+		print (-1n) ** 18446744073709551617n;
+		print 5n ** 0n;
+	"#;
+	assert_eq!(run_ok(source), ["1", "0", "1", "1", "1", "-1", "-1", "1"]);
+}
+
+#[test]
+fn powers_too_large_to_hold_are_errors() {
+	for source in ["print 2n ** 4000000000n;", "print (-2n) ** 18446744073709551616n;", "print (2n ** 1000) ** 2000;"] {
+		let error = run_err(source);
+		assert!(error.contains("Exponent too large."), "{}: {}", source, error);
+	}
+	assert_eq!(run_ok("print (2n ** 1000) ** 3 == 2n ** 3000;"), ["true"]);
+}
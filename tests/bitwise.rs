@@ -0,0 +1,28 @@
+mod common;
+
+use common::{run_err, run_ok};
+
+#[test]
+fn integer_operands_give_integers() {
+	let source = "print 6 & 3; print 6 | 3; print 6 ^ 3; print ~5; print 1 << 60; print -16 >> 2; print type(6 & 3);";
+	assert_eq!(run_ok(source), ["2", "7", "5", "-6", "1152921504606846976", "-4", "int"]);
+}
+
+#[test]
+fn float_operands_give_exact_floats() {
+	let source = "print 6.0 & 3; print ~0.0; print 1.0 << 53; print -1.0 << 53; print type(6.0 & 3);";
+	assert_eq!(run_ok(source), ["2", "-1", "9007199254740992", "-9007199254740992", "number"]);
+}
+
+#[test]
+fn float_results_past_2_pow_53_are_errors() {
+	assert!(run_err("print 1.0 << 54;").contains("Result must not exceed 2^53 in magnitude."));
+	assert!(run_err("print 1 << 60.0;").contains("Result must not exceed 2^53 in magnitude."));
+	assert!(run_err("print 9007199254740992.0 | 1;").contains("Result must not exceed 2^53 in magnitude."));
+}
+
+#[test]
+fn inexact_operands_are_errors() {
+	assert!(run_err("print 1.5 & 1;").contains("Operands must be integers."));
+	assert!(run_err("print 18014398509481984.0 & 1;").contains("Operands must not exceed 2^53 in magnitude."));
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{run_err, run_ok};
+
+#[test]
+fn block_comments_nest() {
+	let source = r#"
+		print 1; /* a comment */ print 2;
+		/* outer /* inner */ still a comment print 3; */
+		print 4;
+		/**/ print 5; /***/
+	"#;
+	assert_eq!(run_ok(source), ["1", "2", "4", "5"]);
+}
+
+#[test]
+fn block_comments_count_lines() {
+	let error = run_err("/* one\ntwo\n/* three */\n*/\nprint 1;\nprint \"a\\q\";\n");
+	assert!(error.contains("Line 6"), "{}", error);
+}
+
+#[test]
+fn unterminated_nested_comment_reports_where_it_opened() {
+	let error = run_err("print 1;\n/* outer\n/* inner */\nprint 2;\n");
+	assert!(error.contains("Line 2"), "{}", error);
+	assert!(error.contains("Unterminated block comment."));
+
+	let error = run_err("print 1; /* /* */");
+	assert!(error.contains("Line 1"), "{}", error);
+	assert!(error.contains("Unterminated block comment."));
+}
+
+#[test]
+fn line_comments_inside_block_comments_are_ignored() {
+	let source = "/* // */ print 1;";
+	assert_eq!(run_ok(source), ["1"]);
+}
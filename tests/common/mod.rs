@@ -0,0 +1,53 @@
+// Each test binary uses only some of these helpers.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// What running a script printed, and whether it ran to completion.
+pub struct Output {
+	pub stdout: String,
+	pub stderr: String,
+	pub success: bool,
+}
+
+/// Runs `source` through the interpreter binary, which cargo builds with
+/// the same features as the tests.
+pub fn run(source: &str) -> Output {
+	static SCRIPTS: AtomicUsize = AtomicUsize::new(0);
+
+	let path: PathBuf = std::env::temp_dir().join(format!(
+		"rlox-test-{}-{}.lox",
+		std::process::id(),
+		SCRIPTS.fetch_add(1, Ordering::Relaxed),
+	));
+	fs::write(&path, source).expect("failed to write script");
+
+	let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+		.arg(&path)
+		.output()
+		.expect("failed to run rlox");
+	let _ = fs::remove_file(&path);
+
+	Output {
+		stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+		stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+		success: output.status.success(),
+	}
+}
+
+/// Runs `source`, which must succeed, and returns its printed lines.
+pub fn run_ok(source: &str) -> Vec<String> {
+	let output = run(source);
+	assert!(output.success, "script failed: {}", output.stderr);
+	output.stdout.lines().map(str::to_string).collect()
+}
+
+/// Runs `source`, which must fail, and returns the error it reported.
+pub fn run_err(source: &str) -> String {
+	let output = run(source);
+	assert!(!output.success, "script succeeded: {}", output.stdout);
+	output.stderr
+}
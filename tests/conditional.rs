@@ -0,0 +1,57 @@
+mod common;
+
+use common::{run, run_ok};
+
+#[test]
+fn chooses_a_branch() {
+	let source = r#"
+		print true ? "yes" : "no";
+		print nil ? "yes" : "no";
+		print 1 < 2 ? 1 + 1 : 0;
+	"#;
+	assert_eq!(run_ok(source), ["yes", "no", "2"]);
+}
+
+#[test]
+fn nests_to_the_right() {
+	let source = r#"
+		fun sign(n) { return n < 0 ? "negative" : n == 0 ? "zero" : "positive"; }
+		print sign(-5);
+		print sign(0);
+		print sign(5);
+
+		// Groups as `false ? 1 : (true ? 2 : 3)`, not `(false ? 1 : true) ? 2 : 3`.
+		print false ? 1 : true ? 2 : 3;
+	"#;
+	assert_eq!(run_ok(source), ["negative", "zero", "positive", "2"]);
+}
+
+#[test]
+fn evaluates_only_the_chosen_branch() {
+	let source = r#"
+		fun say(s) { print s; return s; }
+		var x = false ? say("then") : say("else");
+		print x;
+	"#;
+	assert_eq!(run_ok(source), ["else", "else"]);
+}
+
+#[test]
+fn binds_looser_than_or_and_tighter_than_assignment() {
+	let source = r#"
+		var a;
+		a = false or true ? "left" : "right";
+		print a;
+
+		// As in C, the middle operand is a full expression.
+		var b = true ? a = "assigned" : "other";
+		print a + " " + b;
+	"#;
+	assert_eq!(run_ok(source), ["left", "assigned assigned"]);
+}
+
+#[test]
+fn needs_a_colon() {
+	let output = run("print true ? 1;");
+	assert!(output.stderr.contains("Expect ':' after then branch of conditional expression."));
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::run_ok;
+use rlox::compiler::{Compiler, GlobalNames};
+use rlox::memory::Heap;
+
+/// A script whose first `count` constants are number literals, so that
+/// everything after them needs a constant index past 255.
+fn with_literals(count: usize, rest: &str) -> String {
+	let mut source = String::new();
+	for i in 0..count {
+		source.push_str(&format!("{}.5;\n", i));
+	}
+	source + rest
+}
+
+#[test]
+fn literals_past_255_keep_their_values() {
+	let source = with_literals(300, "print 299.5;\nprint 1000.5;\n");
+	assert_eq!(run_ok(&source), ["299.5", "1000.5"]);
+}
+
+#[test]
+fn classes_functions_and_properties_past_255_constants() {
+	let source = with_literals(300, r#"
+		class A {
+			init(x) { this.x = x; }
+			get() { return this.x; }
+		}
+		class B < A {
+			get() { return super.get() + 1; }
+			bound() { var method = super.get; return method(); }
+		}
+		fun f() { return "f"; }
+
+		var b = B(41);
+		print b.get();
+		print b.bound();
+		print b.x;
+		b.x = 1;
+		print b.x;
+		print f();
+	"#);
+	assert_eq!(run_ok(&source), ["42", "41", "41", "1", "f"]);
+}
+
+#[test]
+fn names_are_added_once_per_chunk() {
+	let mut heap = Heap::new();
+	let mut globals = GlobalNames::new();
+	let source = "var p; p.x; p.x = p.x; p.x(); p.y;";
+	let function = Compiler::new(source, &mut heap, &mut globals).compile()
+		.expect("script should compile")
+		.expect("script should produce a function");
+
+	let names: Vec<_> = function.chunk.constants.iter()
+		.map(|constant| constant.display(&heap).to_string())
+		.collect();
+	assert_eq!(names, ["x", "y"]);
+}
@@ -0,0 +1,111 @@
+//! Integers cover the full `i64` range whichever `Value` representation
+//! the interpreter is built with; CI runs these with and without
+//! `nan_boxing`.
+
+mod common;
+
+use common::{run_err, run_ok};
+
+#[test]
+fn integers_past_the_nan_boxed_payload() {
+	let source = r#"
+		print 2 ** 48;
+		var x = 281474976710655;
+		x++;
+		print x;
+		print 9007199254740993;
+		print type(9007199254740993);
+		print 9223372036854775807;
+		print -9223372036854775807 - 1;
+	"#;
+	assert_eq!(run_ok(source), [
+		"281474976710656",
+		"281474976710656",
+		"9007199254740993",
+		"int",
+		"9223372036854775807",
+		"-9223372036854775808",
+	]);
+}
+
+#[test]
+fn wide_integers_compare_and_promote_by_value() {
+	let source = r#"
+		var big = 2 ** 60;
+		print big == 1152921504606846976;
+		print big == 2 ** 60;
+		print big == 2.0 ** 60;
+		print big != big + 1;
+		print big > 2 ** 59;
+		print big < 2.0 ** 61;
+		print big ~/ 2 ** 30;
+		print big % 1000;
+		print -big;
+		print big / 2 ** 59;
+		print big + 0.5;
+		print num("9007199254740993");
+	"#;
+	assert_eq!(run_ok(source), [
+		"true", "true", "true", "true", "true", "true",
+		"1073741824",
+		"976",
+		"-1152921504606846976",
+		"2",
+		"1152921504606847000",
+		"9007199254740993",
+	]);
+}
+
+#[test]
+fn wide_integers_survive_collection() {
+	let source = r#"
+		var values = nil;
+		class Node { init(value, next) { this.value = value; this.next = next; } }
+		for (var i = 0; i < 200; i++) {
+			values = Node(9007199254740993 + i, values);
+		}
+		var sum = 0;
+		while (values != nil) {
+			sum = sum + (values.value - 9007199254740993);
+			values = values.next;
+		}
+		print sum;
+	"#;
+	assert_eq!(run_ok(source), ["19900"]);
+}
+
+#[test]
+fn overflow_past_i64_is_an_error() {
+	assert!(run_err("print 9223372036854775807 + 1;").contains("Integer overflow."));
+	assert!(run_err("print -9223372036854775807 - 2;").contains("Integer overflow."));
+	assert!(run_err("print 2 ** 63;").contains("Integer overflow."));
+	assert!(run_err("print 2 ** 5000000000;").contains("Integer overflow."));
+}
+
+#[test]
+fn powers_overflow_only_when_the_result_does() {
+	let source = r#"
+		print (-2) ** 63;
+		print 1 ** 9223372036854775807;
+		print 0 ** 9223372036854775807;
+		print (-1) ** 9223372036854775806;
+		print (-1) ** 9223372036854775807;
+		print 0 ** 0;
+		print type((-1) ** 5000000001);
+	"#;
+	assert_eq!(run_ok(source), ["-9223372036854775808", "1", "0", "1", "-1", "1", "int"]);
+}
+
+#[test]
+fn shifting_out_bits_overflows() {
+	assert_eq!(run_ok("print 1 << 62; print -1 << 63; print 5 << 0; print -3 << 2;"), [
+		"4611686018427387904",
+		"-9223372036854775808",
+		"5",
+		"-12",
+	]);
+	assert!(run_err("print 1 << 63;").contains("Integer overflow."));
+	assert!(run_err("print 3 << 62;").contains("Integer overflow."));
+	assert!(run_err("print -3 << 62;").contains("Integer overflow."));
+	assert!(run_err("print 1 << 64;").contains("Shift amount must be between 0 and 63."));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::{run, run_ok};
+
+#[test]
+fn prefixed_literals_and_separators() {
+	let source = "print 0xff; print 0XFF; print 0b1010; print 0o17; print 1_000_000; print 1_0.2_5; print 2.5e3; print 1E-2;";
+	assert_eq!(run_ok(source), ["255", "255", "10", "15", "1000000", "10.25", "2500", "0.01"]);
+}
+
+#[test]
+fn wide_integer_literals_that_are_exact() {
+	let source = r#"
+		print 0x1_0000_0000_0000_0000_0000_0000_0000_0000 == 2.0 ** 128;
+		print 340282366920938463463374607431768211456 == 2.0 ** 128;
+		print 0x8 * 16.0 ** 255 == 2.0 ** 1023;
+		print 0b1_0000000000_0000000000_0000000000_0000000000_0000000000_00 == 2 ** 52;
+		print 0000;
+	"#;
+	assert_eq!(run_ok(source), ["true", "true", "true", "true", "0"]);
+}
+
+#[test]
+fn inexact_integer_literals_are_errors() {
+	let zeros = "0".repeat(256);
+	for literal in ["18446744073709551617".to_string(), "0x1_0000_0000_0000_0001".to_string(), format!("0x1{}", zeros)] {
+		let output = run(&format!("print {};", literal));
+		assert_eq!(output.stdout, "");
+		assert!(output.stderr.contains("Integer literal can't be represented exactly as a number."), "{}", literal);
+	}
+}
+
+#[test]
+fn literals_that_overflow_are_errors() {
+	let output = run("print 1e400;");
+	assert_eq!(output.stdout, "");
+	assert!(output.stderr.contains("Number literal is too large."));
+
+	let output = run("print 2e308;");
+	assert_eq!(output.stdout, "");
+	assert!(output.stderr.contains("Number literal is too large."));
+}
+
+#[test]
+fn integer_literals_past_i64_are_floats() {
+	let source = "print type(9223372036854775807); print type(9223372036854775808); print 0x8000_0000_0000_0000 == 2.0 ** 63;";
+	assert_eq!(run_ok(source), ["int", "number", "true"]);
+}
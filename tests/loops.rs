@@ -0,0 +1,82 @@
+mod common;
+
+use common::{run, run_ok};
+
+#[test]
+fn labeled_continue_skips_to_the_next_outer_iteration() {
+	let source = r#"
+		outer: for (var i = 0; i < 3; i = i + 1) {
+			for (var j = 0; j < 3; j = j + 1) {
+				if (j == 1) continue outer;
+				print i * 10 + j;
+			}
+			print "unreached";
+		}
+		print "done";
+	"#;
+	assert_eq!(run_ok(source), ["0", "10", "20", "done"]);
+}
+
+#[test]
+fn labeled_break_leaves_the_outer_loop() {
+	let source = r#"
+		var i = 0;
+		outer: while (true) {
+			var j = 0;
+			while (true) {
+				if (i == 2) break outer;
+				if (j == 1) break;
+				print i * 10 + j;
+				j = j + 1;
+			}
+			i = i + 1;
+		}
+		print i;
+	"#;
+	assert_eq!(run_ok(source), ["0", "10", "2"]);
+}
+
+#[test]
+fn do_while_runs_the_body_before_the_condition() {
+	let source = r#"
+		var i = 10;
+		do {
+			print i;
+			i = i + 1;
+		} while (i < 3);
+
+		var n = 0;
+		do {
+			n = n + 1;
+			if (n == 2) continue;
+			if (n == 4) break;
+			print n;
+		} while (n < 10);
+	"#;
+	assert_eq!(run_ok(source), ["10", "1", "3"]);
+}
+
+#[test]
+fn continue_in_a_loop_closes_captured_locals() {
+	let source = r#"
+		var first;
+		for (var i = 0; i < 2; i = i + 1) {
+			var local = i;
+			fun get() { return local; }
+			if (first == nil) { first = get; continue; }
+			print get();
+		}
+		print first();
+	"#;
+	assert_eq!(run_ok(source), ["1", "0"]);
+}
+
+#[test]
+fn labels_must_name_an_enclosing_loop() {
+	let output = run("outer: while (false) {} while (true) { break outer; }");
+	assert_eq!(output.stdout, "");
+	assert!(output.stderr.contains("No enclosing loop labeled 'outer'."));
+
+	let output = run("break;");
+	assert!(output.stderr.contains("Can't use 'break' outside of a loop"));
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{run_err, run_ok};
+
+#[test]
+fn simple_escapes() {
+	let source = r#"print "tab\there"; print "quote \" and backslash \\"; print "two\nlines";"#;
+	assert_eq!(run_ok(source), ["tab\there", "quote \" and backslash \\", "two", "lines"]);
+}
+
+#[test]
+fn unicode_escapes() {
+	let source = r#"print "\u{41}\u{e9}\u{1F600}"; print "\u{000041}";"#;
+	assert_eq!(run_ok(source), ["A\u{e9}\u{1F600}", "A"]);
+}
+
+#[test]
+fn escapes_count_toward_string_equality() {
+	let source = r#"print "\u{61}b" == "ab"; print "\n" == "
+";"#;
+	assert_eq!(run_ok(source), ["true", "true"]);
+}
+
+#[test]
+fn invalid_unicode_escapes() {
+	assert!(run_err(r#"print "\u41";"#).contains("Expect '{' after '\\u'."));
+	assert!(run_err(r#"print "\u{}";"#).contains("Invalid Unicode escape sequence."));
+	assert!(run_err(r#"print "\u{1234567}";"#).contains("Invalid Unicode escape sequence."));
+	assert!(run_err(r#"print "\u{4G}";"#).contains("Invalid Unicode escape sequence."));
+	assert!(run_err(r#"print "\u{D800}";"#).contains("Invalid Unicode code point."));
+	assert!(run_err(r#"print "\u{110000}";"#).contains("Invalid Unicode code point."));
+}
+
+#[test]
+fn invalid_escapes_report_their_line() {
+	let error = run_err("print 1;\nprint \"a\\qb\";\n");
+	assert!(error.contains("Line 2"), "{}", error);
+	assert!(error.contains("Invalid escape sequence."));
+}
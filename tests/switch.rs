@@ -0,0 +1,70 @@
+mod common;
+
+use common::{run, run_ok};
+
+#[test]
+fn break_leaves_a_jump_table_switch() {
+	let source = r#"
+		for (var i = 0; i < 4; i = i + 1) {
+			switch (i) {
+				case 1: print "one"; break; print "unreached";
+				case 2: { var x = "two"; print x; break; }
+				default: print i;
+			}
+		}
+	"#;
+	assert_eq!(run_ok(source), ["0", "one", "two", "3"]);
+}
+
+#[test]
+fn break_leaves_a_chained_switch() {
+	let source = r#"
+		for (var i = 0; i < 3; i = i + 1) {
+			switch (i + 0.5) {
+				case 1.5: var local = "one"; print local; break; print "unreached";
+				default: print i;
+			}
+		}
+	"#;
+	assert_eq!(run_ok(source), ["0", "one", "2"]);
+}
+
+#[test]
+fn continue_and_labeled_break_refer_to_loops() {
+	let source = r#"
+		var i = 0;
+		while (i < 4) {
+			i = i + 1;
+			switch (i) { case 2: continue; }
+			print i;
+		}
+
+		outer: for (var j = 0; j < 3; j = j + 1) {
+			switch (j) { case 1: break outer; default: print "j"; }
+		}
+		print "done";
+	"#;
+	assert_eq!(run_ok(source), ["1", "3", "4", "j", "done"]);
+}
+
+#[test]
+fn break_closes_captured_case_locals() {
+	let source = r#"
+		fun make() {
+			var f;
+			switch (1) {
+				case 1: var captured = "kept"; fun get() { return captured; } f = get; break;
+			}
+			return f;
+		}
+		print make()();
+	"#;
+	assert_eq!(run_ok(source), ["kept"]);
+}
+
+#[test]
+fn continue_needs_a_loop_even_in_a_switch() {
+	let output = run("switch (1) { case 1: continue; } print 1;");
+	assert_eq!(output.stdout, "");
+	assert!(output.stderr.contains("Can't use 'continue' outside of a loop."));
+}